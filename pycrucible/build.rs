@@ -1,8 +1,8 @@
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-fn resolve_runner_path(manifest_dir: &PathBuf, target: &str, profile: &str) -> PathBuf {
+fn resolve_runner_path(manifest_dir: &Path, target: &str, profile: &str) -> PathBuf {
     let bin_name = if cfg!(windows) {
         "pycrucible_runner.exe"
     } else {
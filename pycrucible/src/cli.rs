@@ -1,4 +1,6 @@
-use clap::Parser;
+use crate::fetch::DEFAULT_INDEX_URL;
+use clap::{Parser, Subcommand};
+use shared::config::SystemUvPolicy;
 use std::env;
 use std::path::PathBuf;
 
@@ -16,15 +18,33 @@ fn get_version() -> &'static str {
 }
 
 #[derive(Parser, Debug)]
-#[command(author = AUTHOR, version = get_version(), about = ABOUT, long_about = None)]
+#[command(
+    author = AUTHOR,
+    version = get_version(),
+    about = ABOUT,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     #[arg(
         short = 'e',
         long,
-        help = "Directory containing Python project to embed.",
-        value_name = "SOURCE_DIR"
+        required = true,
+        help = "Python project to embed: a directory, a wheel, an sdist, a `git+<url>[@<ref>]` URL or a requirement like `httpie==3.2.2`.",
+        value_name = "SOURCE"
+    )]
+    pub embed: Option<PathBuf>,
+
+    #[arg(
+        long,
+        default_value = DEFAULT_INDEX_URL,
+        help = "Package index (a simple index URL or a local directory) requirements given to `--embed` are resolved from."
     )]
-    pub embed: PathBuf,
+    pub index_url: String,
 
     #[arg(
         short = 'o',
@@ -42,10 +62,50 @@ pub struct Cli {
 
     #[arg(
         long,
-        help = "Path to `uv` executable. If not found, it will be downloaded automatically"
+        help = "Version of `uv` to embed. Either an exact version or a semver range matched against installed and cached `uv` binaries. Overrides `uv_version` from the configuration."
+    )]
+    pub uv_version: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Configuration file applied on top of `[tool.pycrucible]` and `pycrucible.toml`. Command line flags still take precedence."
+    )]
+    pub config: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Apply the `[profiles.<PROFILE>]` section of the configuration. The profile name is recorded in the built binary."
+    )]
+    pub profile: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Console script of the embedded wheel to run. Overrides `entrypoint` from the configuration."
+    )]
+    pub console_script: Option<String>,
+
+    #[arg(
+        long = "extra",
+        value_name = "EXTRA",
+        help = "Extra of the embedded wheel to install. Can be repeated, replaces `wheel.extras` from the configuration."
     )]
-    #[arg(default_value_t = String::from("0.9.21"))]
-    pub uv_version: String,
+    pub extras: Vec<String>,
+
+    #[arg(
+        long = "wheel",
+        value_name = "PATH",
+        help = "Additional wheel to embed and install next to the app wheel, e.g. a private dependency. Can be repeated, replaces `wheel.wheels` from the configuration."
+    )]
+    pub wheels: Vec<PathBuf>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Whether a `uv` found on PATH or next to the builder may be embedded: only with a matching version (`compatible`), `never` or regardless of version (`any`)."
+    )]
+    pub system_uv: Option<SystemUvPolicy>,
 
     #[arg(
         long,
@@ -55,13 +115,13 @@ pub struct Cli {
 
     #[arg(
         long,
-        help = "[`wheel` mode only] Extracts the embedded files to a temporary directory instead of a permanent one at runtime. The temporary directory will be deleted when the program exits."
+        help = "Extracts the embedded files to a temporary directory instead of a permanent one at runtime. The temporary directory will be deleted when the program exits."
     )]
     pub extract_to_temp: bool,
 
     #[arg(
         long,
-        help = "[`wheel` mode only] Deletes the extracted files after the program finishes running. Ignored if `--extract-to-temp` is used."
+        help = "Deletes the extracted files after the program finishes running. Ignored if `--extract-to-temp` is used."
     )]
     pub delete_after_run: bool,
 
@@ -71,10 +131,102 @@ pub struct Cli {
     )]
     pub force_uv_download: bool,
 
+    #[arg(
+        long,
+        help = "Proxy URL used when downloading `uv`. `HTTPS_PROXY` and `NO_PROXY` are honored when not set."
+    )]
+    pub proxy: Option<String>,
+
+    #[arg(
+        long,
+        help = "Path to a PEM bundle with additional CA certificates used when downloading `uv`."
+    )]
+    pub ca_bundle: Option<String>,
+
+    #[arg(
+        long,
+        help = "Mirror for `uv` releases. Either a base URL laid out like GitHub releases or a full URL template with `{version}`, `{target}` and `{ext}` placeholders."
+    )]
+    pub uv_mirror: Option<String>,
+
+    #[arg(
+        long,
+        help = "Expected SHA-256 of the downloaded `uv` archive for the current platform. If not set, the checksum published alongside the release is used."
+    )]
+    pub uv_sha256: Option<String>,
+
+    #[arg(
+        long,
+        help = "List every file of the project, whether it would be embedded and why (patterns, resources or `.gitignore`, `.ignore` and `.pycrucibleignore` files), without building."
+    )]
+    pub list_files: bool,
+
     #[arg(long, help = "Enable debug output")]
     pub debug: bool,
 }
 
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(subcommand, about = "Inspect PyCrucible configuration")]
+    Config(ConfigCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    #[command(
+        about = "Check every configuration layer (`[tool.pycrucible]`, `pycrucible.toml`, `--config` and profiles) for errors"
+    )]
+    Validate {
+        #[arg(
+            default_value = ".",
+            help = "Project directory, or a configuration file validated together with the rest of its project."
+        )]
+        path: PathBuf,
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Configuration file applied on top of the project configuration."
+        )]
+        config: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "Only validate the `[profiles.<PROFILE>]` section instead of every profile."
+        )]
+        profile: Option<String>,
+    },
+    #[command(about = "Print the effective configuration and where each value comes from")]
+    Show {
+        #[arg(default_value = ".", help = "Project directory.")]
+        path: PathBuf,
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Configuration file applied on top of the project configuration."
+        )]
+        config: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "Apply the `[profiles.<PROFILE>]` section of the configuration."
+        )]
+        profile: Option<String>,
+        #[arg(
+            long,
+            value_name = "BINARY",
+            conflicts_with_all = ["config", "profile"],
+            help = "Print the configuration embedded in a built binary instead."
+        )]
+        binary: Option<PathBuf>,
+    },
+    #[command(about = "Print the JSON Schema of the configuration for editor autocompletion")]
+    Schema {
+        #[arg(
+            long,
+            help = "Emit a schema for `pyproject.toml` with the configuration under `[tool.pycrucible]`."
+        )]
+        pyproject: bool,
+    },
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -88,6 +240,38 @@ mod tests {
         assert_eq!(output_dir, expected_dir);
     }
 
+    #[test]
+    fn test_config_subcommand_does_not_require_embed() {
+        let cli = Cli::try_parse_from(["pycrucible", "config", "validate"]).unwrap();
+        assert!(cli.embed.is_none());
+        assert!(matches!(
+            cli.command,
+            Some(Command::Config(ConfigCommand::Validate { .. }))
+        ));
+        assert!(Cli::try_parse_from(["pycrucible"]).is_err());
+    }
+
+    #[test]
+    fn test_wheel_flags_can_be_repeated() {
+        let cli = Cli::try_parse_from([
+            "pycrucible",
+            "-e",
+            "app-1.0-py3-none-any.whl",
+            "--console-script",
+            "app",
+            "--extra",
+            "cli",
+            "--extra",
+            "yaml",
+            "--wheel",
+            "dep-1.0-py3-none-any.whl",
+        ])
+        .unwrap();
+        assert_eq!(cli.console_script.as_deref(), Some("app"));
+        assert_eq!(cli.extras, ["cli", "yaml"]);
+        assert_eq!(cli.wheels, [PathBuf::from("dep-1.0-py3-none-any.whl")]);
+    }
+
     #[test]
     fn test_get_version_matches_env() {
         let version = get_version();
@@ -0,0 +1,421 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::cli::ConfigCommand;
+use crate::config::{LayeredConfig, ProjectConfig, load_layered_config};
+use crate::project::{compile_resources, validate_patterns};
+
+// Checks what deserializing does not: include, exclude and resource globs, and entrypoints or
+// hooks naming no or several kinds
+fn check_values(config: &ProjectConfig) -> Result<(), String> {
+    let package = &config.package;
+    validate_patterns(&package.patterns.include, &package.patterns.exclude)?;
+    compile_resources(&package.resources)?;
+
+    if let Some(entrypoint) = package.default_entrypoint() {
+        entrypoint
+            .kind()
+            .map_err(|e| format!("entrypoint: {}", e))?;
+    }
+    for (name, entrypoint) in &package.entrypoints {
+        entrypoint
+            .kind()
+            .map_err(|e| format!("entrypoints.{}: {}", name, e))?;
+    }
+
+    let platform_hooks = config.platform.sections().filter_map(|(name, section)| {
+        Some((format!("platform.{}.hooks", name), section.hooks.as_ref()?))
+    });
+    let hooks = config
+        .hooks
+        .as_ref()
+        .map(|hooks| ("hooks".to_string(), hooks))
+        .into_iter()
+        .chain(platform_hooks);
+    for (prefix, hooks) in hooks {
+        for (point, hook) in hooks.all() {
+            hook.kind()
+                .map_err(|e| format!("{}.{}: {}", prefix, point, e))?;
+        }
+    }
+    Ok(())
+}
+
+// The configuration layers found for `project_dir`, in the order they are merged
+fn layer_names(project_dir: &Path, config_file: Option<&Path>) -> Result<Vec<String>, String> {
+    let mut layers = Vec::new();
+    if ProjectConfig::from_pyproject(&project_dir.join("pyproject.toml"))
+        .ok()
+        .flatten()
+        .is_some()
+    {
+        layers.push("[tool.pycrucible] in pyproject.toml".to_string());
+    }
+    if project_dir.join("pycrucible.toml").is_file() {
+        layers.push("pycrucible.toml".to_string());
+    }
+    if let Some(config_file) = config_file {
+        layers.push(config_file.display().to_string());
+    }
+    Ok(layers)
+}
+
+// Validates every layer a build of the project would merge, each profile applied on its own
+fn validate(path: &Path, config_file: Option<&Path>, profile: Option<&str>) -> Result<(), String> {
+    if !path.exists() {
+        return Err(format!("{} does not exist", path.display()));
+    }
+    // A single file is validated together with the other layers of its project
+    let (project_dir, config_file) = if path.is_dir() {
+        (path, config_file)
+    } else {
+        let project_dir = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let file_name = path.file_name().and_then(|s| s.to_str());
+        if matches!(file_name, Some("pyproject.toml" | "pycrucible.toml")) {
+            (project_dir, config_file)
+        } else {
+            (project_dir, config_file.or(Some(path)))
+        }
+    };
+
+    let layered = load_layered_config(project_dir, config_file, None, toml::Table::new())?;
+    check_values(&layered.config)
+        .map_err(|e| format!("Invalid configuration in {}\n{}", project_dir.display(), e))?;
+    let profiles: Vec<&str> = match profile {
+        Some(profile) => vec![profile],
+        None => layered.profiles.iter().map(String::as_str).collect(),
+    };
+    for name in &profiles {
+        let layered =
+            load_layered_config(project_dir, config_file, Some(name), toml::Table::new())?;
+        check_values(&layered.config).map_err(|e| {
+            format!(
+                "Invalid configuration in {} with profile `{}`\n{}",
+                project_dir.display(),
+                name,
+                e
+            )
+        })?;
+    }
+
+    let layers = layer_names(project_dir, config_file)?;
+    if layers.is_empty() {
+        println!(
+            "No pycrucible.toml or [tool.pycrucible] found in {}, the built-in defaults would be used",
+            project_dir.display()
+        );
+    } else if profiles.is_empty() {
+        println!("✔ Configuration is valid ({})", layers.join(", "));
+    } else {
+        println!(
+            "✔ Configuration is valid ({}, profiles: {})",
+            layers.join(", "),
+            profiles.join(", ")
+        );
+    }
+    Ok(())
+}
+
+// The pycrucible.toml the builder wrote into the payload of `binary`
+fn read_embedded_config(binary: &Path) -> Result<String, String> {
+    let error = |e: &dyn std::fmt::Display| format!("{}: {}", binary.display(), e);
+    let info = shared::footer::read_footer_from(binary).map_err(|e| error(&e))?;
+    let mut file = File::open(binary).map_err(|e| error(&e))?;
+    let payload_end =
+        file.metadata().map_err(|e| error(&e))?.len() - shared::footer::FOOTER_SIZE as u64;
+    file.seek(SeekFrom::Start(info.offset))
+        .map_err(|e| error(&e))?;
+    let mut payload = Vec::new();
+    file.take(payload_end.saturating_sub(info.offset))
+        .read_to_end(&mut payload)
+        .map_err(|e| error(&e))?;
+
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(payload)).map_err(|e| error(&e))?;
+    let mut embedded = archive.by_name("pycrucible.toml").map_err(|e| error(&e))?;
+    let mut content = String::new();
+    embedded
+        .read_to_string(&mut content)
+        .map_err(|e| error(&e))?;
+    Ok(content)
+}
+
+// One `key = value  # origin` line per value, origins aligned
+fn describe(layered: &LayeredConfig) -> String {
+    let lines: Vec<(String, &str)> = layered
+        .values
+        .iter()
+        .map(|(path, effective)| {
+            (
+                format!("{} = {}", path, effective.value),
+                effective.origin.as_str(),
+            )
+        })
+        .collect();
+    // Long arrays should not push every other origin off screen
+    let width = lines
+        .iter()
+        .map(|(line, _)| line.len())
+        .filter(|len| *len <= 48)
+        .max()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|(line, origin)| format!("{:width$}  # {}", line, origin, width = width))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Nests the configuration schema under `tool.pycrucible`, other keys stay unrestricted
+fn pyproject_schema(config_schema: serde_json::Value) -> serde_json::Value {
+    let mut config_schema = config_schema;
+    let object = config_schema
+        .as_object_mut()
+        .expect("schema is a JSON object");
+    let meta_schema = object.remove("$schema");
+    let defs = object.remove("$defs");
+
+    let mut schema = serde_json::json!({
+        "title": "pyproject.toml with PyCrucible configuration",
+        "type": "object",
+        "properties": {
+            "tool": {
+                "type": "object",
+                "properties": {
+                    "pycrucible": config_schema
+                }
+            }
+        }
+    });
+    let root = schema.as_object_mut().unwrap();
+    if let Some(meta_schema) = meta_schema {
+        root.insert("$schema".to_string(), meta_schema);
+    }
+    if let Some(defs) = defs {
+        root.insert("$defs".to_string(), defs);
+    }
+    schema
+}
+
+pub fn run(command: ConfigCommand) -> Result<(), String> {
+    match command {
+        ConfigCommand::Validate {
+            path,
+            config,
+            profile,
+        } => validate(&path, config.as_deref(), profile.as_deref()),
+        ConfigCommand::Show {
+            binary: Some(binary),
+            ..
+        } => {
+            print!("{}", read_embedded_config(&binary)?);
+            Ok(())
+        }
+        ConfigCommand::Show {
+            path,
+            config,
+            profile,
+            binary: None,
+        } => {
+            let layered = load_layered_config(
+                &path,
+                config.as_deref(),
+                profile.as_deref(),
+                toml::Table::new(),
+            )?;
+            println!("{}", describe(&layered));
+            Ok(())
+        }
+        ConfigCommand::Schema { pyproject } => {
+            let schema =
+                serde_json::to_value(ProjectConfig::json_schema()).map_err(|e| e.to_string())?;
+            let schema = if pyproject {
+                pyproject_schema(schema)
+            } else {
+                schema
+            };
+            let output = serde_json::to_string_pretty(&schema).map_err(|e| e.to_string())?;
+            println!("{}", output);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_pyproject_schema_nests_config_under_tool() {
+        let schema = serde_json::to_value(ProjectConfig::json_schema()).unwrap();
+        let wrapped = pyproject_schema(schema);
+
+        let pycrucible = &wrapped["properties"]["tool"]["properties"]["pycrucible"];
+        assert!(pycrucible["properties"]["entrypoint"].is_object());
+        assert_eq!(pycrucible["additionalProperties"], false);
+        assert!(pycrucible.get("$defs").is_none());
+        assert!(wrapped["$defs"]["ToolOptions"].is_object());
+    }
+
+    #[test]
+    fn test_describe_lists_origins() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("pycrucible.toml"),
+            "entrypoint = \"app.py\"\n",
+        )
+        .unwrap();
+        let layered = load_layered_config(dir.path(), None, None, toml::Table::new()).unwrap();
+        let description = describe(&layered);
+
+        let entrypoint = description
+            .lines()
+            .find(|line| line.starts_with("entrypoint = "))
+            .unwrap();
+        assert!(entrypoint.contains("\"app.py\""));
+        assert!(entrypoint.ends_with("# pycrucible.toml"));
+        let debug = description
+            .lines()
+            .find(|line| line.starts_with("options.debug = "))
+            .unwrap();
+        assert!(debug.ends_with("# default"));
+    }
+
+    #[test]
+    fn test_read_embedded_config_from_binary() {
+        use std::io::Write;
+
+        let dir = tempdir().unwrap();
+        let binary = dir.path().join("launcher");
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        {
+            let mut zip = zip::ZipWriter::new(&mut cursor);
+            zip.start_file("pycrucible.toml", zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(b"entrypoint = \"main.py\"\nprofile = \"prod\"\n")
+                .unwrap();
+            zip.finish().unwrap();
+        }
+        let runner = b"runner-binary";
+        let mut content = runner.to_vec();
+        content.extend_from_slice(&cursor.into_inner());
+        content.extend_from_slice(&shared::footer::create_footer(false, runner.len() as u64));
+        fs::write(&binary, content).unwrap();
+
+        let embedded = read_embedded_config(&binary).unwrap();
+        assert!(embedded.contains("profile = \"prod\""));
+        assert!(read_embedded_config(&dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_validate_reports_invalid_config() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("pycrucible.toml"),
+            "entrypoint = \"main.py\"\n[patterns]\ninclude = [\"src/[abc\"]\n",
+        )
+        .unwrap();
+        assert!(
+            validate(dir.path(), None, None)
+                .unwrap_err()
+                .contains("src/[abc")
+        );
+
+        fs::write(
+            dir.path().join("pycrucible.toml"),
+            "entrypoint = \"main.py\"\n",
+        )
+        .unwrap();
+        assert!(validate(dir.path(), None, None).is_ok());
+        assert!(validate(&dir.path().join("pycrucible.toml"), None, None).is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_ambiguous_entrypoints_and_hooks() {
+        let dir = tempdir().unwrap();
+        let config = dir.path().join("pycrucible.toml");
+        for (content, expected) in [
+            (
+                "entrypoint = { module = \"app.cli\", script = \"main.py\" }\n",
+                "entrypoint",
+            ),
+            ("[entrypoints]\nadmin = {}\n", "entrypoints.admin"),
+            (
+                "[hooks]\npre_run = { script = \"setup.py\", command = \"make\" }\n",
+                "hooks.pre_run",
+            ),
+            (
+                "[platform.windows.hooks]\non_error = [\"report.py\", { args = [\"-v\"] }]\n",
+                "platform.windows.hooks.on_error",
+            ),
+            (
+                "[profiles.prod.hooks]\npost_run = { module = \"a\", command = \"b\" }\n",
+                "profile `prod`",
+            ),
+        ] {
+            fs::write(&config, content).unwrap();
+            let err = validate(dir.path(), None, None).unwrap_err();
+            assert!(err.contains(expected), "{}: {}", expected, err);
+        }
+
+        fs::write(
+            &config,
+            "entrypoint = \"admin\"\n[entrypoints]\nadmin = { module = \"app.admin\" }\n[hooks]\npre_run = \"setup.py\"\n",
+        )
+        .unwrap();
+        assert!(validate(dir.path(), None, None).is_ok());
+    }
+
+    #[test]
+    fn test_validate_checks_every_layer() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("pycrucible.toml"),
+            "entrypoint = \"main.py\"\n",
+        )
+        .unwrap();
+        // pycrucible.toml alone is valid, the typo is in the layer below it
+        fs::write(
+            dir.path().join("pyproject.toml"),
+            "[project]\nname = \"app\"\n\n[tool.pycrucible]\nentry_point = \"app.py\"\n",
+        )
+        .unwrap();
+        let err = validate(&dir.path().join("pycrucible.toml"), None, None).unwrap_err();
+        assert!(err.contains("pyproject.toml"));
+        assert!(err.contains("entry_point"));
+
+        fs::write(
+            dir.path().join("pyproject.toml"),
+            "[tool.pycrucible]\nresources = [\"data/[abc\"]\n\n[tool.pycrucible.profiles.prod.patterns]\ninclude = [\"src/[xyz\"]\n",
+        )
+        .unwrap();
+        let err = validate(dir.path(), None, None).unwrap_err();
+        assert!(err.contains("data/[abc"));
+
+        fs::write(
+            dir.path().join("pyproject.toml"),
+            "[tool.pycrucible.profiles.prod.patterns]\ninclude = [\"src/[xyz\"]\n",
+        )
+        .unwrap();
+        let err = validate(dir.path(), None, None).unwrap_err();
+        assert!(err.contains("profile `prod`"));
+        assert!(err.contains("src/[xyz"));
+
+        // An explicit --config file is a layer too
+        fs::write(
+            dir.path().join("pyproject.toml"),
+            "[project]\nname = \"app\"\n",
+        )
+        .unwrap();
+        let extra = dir.path().join("ci.toml");
+        fs::write(&extra, "[options]\ndebugg = true\n").unwrap();
+        assert!(validate(dir.path(), None, None).is_ok());
+        assert!(validate(dir.path(), Some(&extra), None).is_err());
+        assert!(validate(&extra, None, None).is_err());
+    }
+}
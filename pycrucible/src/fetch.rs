@@ -0,0 +1,658 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use reqwest::Url;
+use shared::config::ToolOptions;
+use shared::debug_println;
+use shared::uv_handler::{RetryPolicy, build_client, download, find_or_download_uv, sha256_hex};
+use tempfile::TempDir;
+
+use crate::requirement::{DistributionFile, Requirement, normalize_name};
+
+pub const DEFAULT_INDEX_URL: &str = "https://pypi.org/simple";
+
+/// What `--embed` points at once it is available locally: a project directory or a wheel.
+pub struct EmbedSource {
+    pub path: PathBuf,
+    // Extras requested with the requirement, e.g. `httpie[socks]`
+    pub extras: Vec<String>,
+    // Downloads, clones and builds, removed once the binary is written
+    _workdir: Option<TempDir>,
+}
+
+/// `git+<url>[@<ref>][#subdirectory=<path>]`, like pip and uv accept them.
+#[derive(Debug, PartialEq)]
+struct GitSource {
+    url: String,
+    reference: Option<String>,
+    subdirectory: Option<String>,
+}
+
+/// A file offered by a package index.
+#[derive(Debug)]
+struct IndexFile {
+    file_name: String,
+    // URL, or the path for local indexes
+    location: String,
+    sha256: Option<String>,
+}
+
+fn parse_git_source(spec: &str) -> Option<GitSource> {
+    let rest = spec.strip_prefix("git+")?;
+    let (rest, fragment) = match rest.split_once('#') {
+        Some((rest, fragment)) => (rest, Some(fragment)),
+        None => (rest, None),
+    };
+    let subdirectory = fragment.and_then(|fragment| {
+        fragment
+            .split('&')
+            .find_map(|part| part.strip_prefix("subdirectory="))
+            .map(str::to_string)
+    });
+    // `@` in the authority is the user of `ssh://git@host/...`, only one after the host starts
+    // a ref, which may contain `/` like `feature/foo`
+    let path_start = match rest.find("://") {
+        Some(scheme_end) => rest[scheme_end + 3..]
+            .find('/')
+            .map_or(rest.len(), |slash| scheme_end + 3 + slash),
+        // scp-like `git@host:path`
+        None => rest.find(':').map_or(0, |colon| colon + 1),
+    };
+    let (url, reference) = match rest[path_start..].rfind('@') {
+        Some(at) => (
+            &rest[..path_start + at],
+            Some(rest[path_start + at + 1..].to_string()),
+        ),
+        None => (rest, None),
+    };
+    Some(GitSource {
+        url: url.to_string(),
+        reference,
+        subdirectory,
+    })
+}
+
+// Release files are named `<name>-<version>.tar.gz` or `.zip`, other archives are no sdists
+fn is_sdist(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    DistributionFile::parse(&name).is_some_and(|dist| dist.platform.is_none())
+}
+
+// Wheel and sdist specs are files, a missing one must not be looked up on the index
+fn is_archive_spec(spec: &str) -> bool {
+    [".whl", ".tar.gz", ".zip"]
+        .iter()
+        .any(|extension| spec.ends_with(extension))
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+/// Clones the repository and checks out the requested branch, tag or commit. Cloning goes
+/// through the `git` CLI, libgit2 is built without network transports and the CLI brings the
+/// user's credential helpers and SSH setup.
+fn clone_git_source(source: &GitSource, dir: &Path) -> io::Result<PathBuf> {
+    println!("Cloning {} ...", source.url);
+    let status = Command::new("git")
+        .args(["clone", "--quiet", "--"])
+        .arg(&source.url)
+        .arg(dir)
+        .status()
+        .map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!(
+                    "Failed to run `git` to clone {}, git+ URLs need git installed: {}",
+                    source.url, e
+                ),
+            )
+        })?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "Failed to clone {}: git clone exited with {}",
+            source.url, status
+        )));
+    }
+    let repo = git2::Repository::open(dir)
+        .map_err(|e| io::Error::other(format!("Failed to open {}: {}", dir.display(), e)))?;
+    if let Some(reference) = &source.reference {
+        let object = repo
+            .revparse_single(reference)
+            .or_else(|_| repo.revparse_single(&format!("origin/{}", reference)))
+            .map_err(|e| {
+                invalid(format!(
+                    "Unknown ref `{}` in {}: {}",
+                    reference, source.url, e
+                ))
+            })?;
+        repo.checkout_tree(&object, Some(git2::build::CheckoutBuilder::new().force()))
+            .and_then(|_| repo.set_head_detached(object.peel_to_commit()?.id()))
+            .map_err(|e| io::Error::other(format!("Failed to check out {}: {}", reference, e)))?;
+    }
+    Ok(match &source.subdirectory {
+        Some(subdirectory) => dir.join(subdirectory),
+        None => dir.to_path_buf(),
+    })
+}
+
+/// Builds a wheel from a project directory or sdist with `uv build`.
+fn build_wheel(uv_path: &Path, source: &Path, out_dir: &Path) -> io::Result<PathBuf> {
+    println!("Building a wheel from {} ...", source.display());
+    let status = Command::new(uv_path)
+        .arg("build")
+        .arg("--wheel")
+        .arg("--out-dir")
+        .arg(out_dir)
+        .arg(source)
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "Failed to build a wheel from {}: uv build exited with {}",
+            source.display(),
+            status
+        )));
+    }
+    fs::read_dir(out_dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .find(|path| path.extension().is_some_and(|ext| ext == "whl"))
+        .ok_or_else(|| {
+            io::Error::other(format!(
+                "uv build produced no wheel for {}",
+                source.display()
+            ))
+        })
+}
+
+// A local index is a directory (or `file://` URL) laid out like a simple index, with a
+// directory per project, or a flat directory of release files
+fn local_index_dir(index: &str) -> Option<PathBuf> {
+    if let Ok(url) = Url::parse(index)
+        && url.scheme() == "file"
+    {
+        return url.to_file_path().ok();
+    }
+    let path = Path::new(index);
+    path.is_dir().then(|| path.to_path_buf())
+}
+
+fn list_local_index(dir: &Path, project: &str) -> io::Result<Vec<IndexFile>> {
+    let project_dir = dir.join(project);
+    let dir = if project_dir.is_dir() {
+        project_dir
+    } else {
+        dir.to_path_buf()
+    };
+    let mut files = Vec::new();
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if let Some(file_name) = path.file_name().and_then(|name| name.to_str()) {
+            files.push(IndexFile {
+                file_name: file_name.to_string(),
+                location: path.to_string_lossy().into_owned(),
+                sha256: None,
+            });
+        }
+    }
+    Ok(files)
+}
+
+// Anchors of a PEP 503 project page, yanked files are skipped
+fn parse_simple_page(html: &str, page_url: &Url) -> Vec<IndexFile> {
+    html.split("<a ")
+        .skip(1)
+        .filter_map(|anchor| {
+            let tag = &anchor[..anchor.find('>')?];
+            if tag.contains("data-yanked") {
+                return None;
+            }
+            let href = tag.split("href=\"").nth(1)?.split('"').next()?;
+            let url = page_url.join(&href.replace("&amp;", "&")).ok()?;
+            let file_name = url.path_segments()?.next_back()?.to_string();
+            let sha256 = url
+                .fragment()
+                .and_then(|fragment| fragment.strip_prefix("sha256="))
+                .map(str::to_string);
+            let mut location = url.clone();
+            location.set_fragment(None);
+            Some(IndexFile {
+                file_name,
+                location: location.to_string(),
+                sha256,
+            })
+        })
+        .collect()
+}
+
+fn list_remote_index(
+    index: &str,
+    project: &str,
+    options: &ToolOptions,
+) -> io::Result<Vec<IndexFile>> {
+    let page_url = Url::parse(&format!("{}/{}/", index.trim_end_matches('/'), project))
+        .map_err(|e| invalid(format!("Invalid index URL {}: {}", index, e)))?;
+    let client = build_client(options).map_err(|e| io::Error::other(e.to_string()))?;
+    let response = client
+        .get(page_url.clone())
+        .header("Accept", "text/html")
+        .send()
+        .map_err(|e| io::Error::other(format!("Failed to query {}: {}", page_url, e)))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("`{}` was not found on {}", project, index),
+        ));
+    }
+    let html = response
+        .error_for_status()
+        .and_then(|response| response.text())
+        .map_err(|e| io::Error::other(format!("Failed to query {}: {}", page_url, e)))?;
+    Ok(parse_simple_page(&html, &page_url))
+}
+
+/// The newest release matching `requirement`, as a pure Python 3 wheel or otherwise an sdist.
+/// Platform specific wheels are not picked, the binary may run on other platforms.
+fn select_release(requirement: &Requirement, files: Vec<IndexFile>) -> Result<IndexFile, String> {
+    let project = normalize_name(&requirement.name);
+    let mut candidates: Vec<(DistributionFile, IndexFile)> = files
+        .into_iter()
+        .filter_map(|file| Some((DistributionFile::parse(&file.file_name)?, file)))
+        .filter(|(dist, _)| dist.name == project && requirement.matches(&dist.version))
+        .filter(|(dist, _)| dist.platform.is_none() || dist.is_pure_wheel())
+        .collect();
+    // Newest first, wheels before sdists of the same version
+    candidates.sort_by(|(a, _), (b, _)| {
+        b.version
+            .cmp(&a.version)
+            .then(b.is_pure_wheel().cmp(&a.is_pure_wheel()))
+    });
+    candidates
+        .into_iter()
+        .next()
+        .map(|(_, file)| file)
+        .ok_or_else(|| {
+            format!(
+                "No pure Python wheel or sdist of `{}` matches the requirement",
+                requirement.name
+            )
+        })
+}
+
+fn fetch_release(file: &IndexFile, dir: &Path, options: &ToolOptions) -> io::Result<PathBuf> {
+    let bytes = if file.location.starts_with("https://") || file.location.starts_with("http://") {
+        download(
+            &file.location,
+            &dir.join(format!("{}.part", file.file_name)),
+            options,
+            &RetryPolicy::default(),
+        )
+        .map_err(|e| io::Error::other(e.to_string()))?
+    } else {
+        fs::read(&file.location)?
+    };
+    if let Some(expected) = &file.sha256 {
+        let actual = sha256_hex(&bytes);
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(io::Error::other(format!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                file.file_name, expected, actual
+            )));
+        }
+    }
+    let path = dir.join(&file.file_name);
+    fs::write(&path, bytes)?;
+    Ok(path)
+}
+
+fn find_uv(uv_path: &Path, options: &ToolOptions) -> io::Result<PathBuf> {
+    find_or_download_uv(Some(uv_path.to_path_buf()), options).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "Could not find or download uv to build a wheel",
+        )
+    })
+}
+
+/// Makes `spec` available locally: directories and wheels are used as they are, sdists and
+/// `git+` URLs are built into a wheel and anything else that parses as a requirement is
+/// downloaded from `index` (a simple index URL or a local directory).
+pub fn resolve_embed_source(
+    spec: &str,
+    index: &str,
+    uv_path: &Path,
+    options: &ToolOptions,
+) -> io::Result<EmbedSource> {
+    let path = Path::new(spec);
+    let workdir = || {
+        tempfile::Builder::new()
+            .prefix("pycrucible-embed-")
+            .tempdir()
+    };
+
+    if is_archive_spec(spec) && !path.is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} does not exist", spec),
+        ));
+    }
+    if path.is_dir() || (path.is_file() && spec.ends_with(".whl")) {
+        return Ok(EmbedSource {
+            path: path.to_path_buf(),
+            extras: Vec::new(),
+            _workdir: None,
+        });
+    }
+    if path.is_file() && is_sdist(path) {
+        let workdir = workdir()?;
+        let wheel = build_wheel(&find_uv(uv_path, options)?, path, workdir.path())?;
+        return Ok(EmbedSource {
+            path: wheel,
+            extras: Vec::new(),
+            _workdir: Some(workdir),
+        });
+    }
+    if path.is_file() && is_archive_spec(spec) {
+        return Err(invalid(format!(
+            "{} is not an sdist, those are named `<name>-<version>.tar.gz` or `.zip`",
+            spec
+        )));
+    }
+    if path.is_file() {
+        return Err(invalid(format!(
+            "{} is neither a project directory, a wheel nor an sdist",
+            spec
+        )));
+    }
+    if let Some(source) = parse_git_source(spec) {
+        let workdir = workdir()?;
+        let checkout = clone_git_source(&source, &workdir.path().join("checkout"))?;
+        let dist = workdir.path().join("dist");
+        let wheel = build_wheel(&find_uv(uv_path, options)?, &checkout, &dist)?;
+        return Ok(EmbedSource {
+            path: wheel,
+            extras: Vec::new(),
+            _workdir: Some(workdir),
+        });
+    }
+
+    // A mistyped directory must not silently turn into a download
+    let looks_like_path = spec.contains(['/', '\\']) || spec.starts_with('.');
+    let requirement = Requirement::parse(spec).filter(|_| !looks_like_path).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "{} is neither an existing directory, wheel or sdist, a git+ URL nor a requirement",
+                spec
+            ),
+        )
+    })?;
+    let project = normalize_name(&requirement.name);
+    println!("Resolving {} from {} ...", spec, index);
+    let files = match local_index_dir(index) {
+        Some(dir) => list_local_index(&dir, &project)?,
+        None => list_remote_index(index, &project, options)?,
+    };
+    let release = select_release(&requirement, files).map_err(invalid)?;
+    debug_println!(
+        "[fetch.resolve_embed_source] - Selected {}",
+        release.location
+    );
+
+    let workdir = workdir()?;
+    let fetched = fetch_release(&release, workdir.path(), options)?;
+    let wheel = if is_sdist(&fetched) {
+        let dist = workdir.path().join("dist");
+        build_wheel(&find_uv(uv_path, options)?, &fetched, &dist)?
+    } else {
+        fetched
+    };
+    Ok(EmbedSource {
+        path: wheel,
+        extras: requirement.extras,
+        _workdir: Some(workdir),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_git_source() {
+        assert_eq!(
+            parse_git_source("git+https://github.com/httpie/cli.git@3.2.2#subdirectory=pkg"),
+            Some(GitSource {
+                url: "https://github.com/httpie/cli.git".to_string(),
+                reference: Some("3.2.2".to_string()),
+                subdirectory: Some("pkg".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_git_source("git+ssh://git@github.com/httpie/cli.git"),
+            Some(GitSource {
+                url: "ssh://git@github.com/httpie/cli.git".to_string(),
+                reference: None,
+                subdirectory: None,
+            })
+        );
+        assert_eq!(
+            parse_git_source("git+https://host/x.git@feature/foo"),
+            Some(GitSource {
+                url: "https://host/x.git".to_string(),
+                reference: Some("feature/foo".to_string()),
+                subdirectory: None,
+            })
+        );
+        assert_eq!(
+            parse_git_source("git+ssh://git@host/x.git@release/2.0#subdirectory=pkg"),
+            Some(GitSource {
+                url: "ssh://git@host/x.git".to_string(),
+                reference: Some("release/2.0".to_string()),
+                subdirectory: Some("pkg".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_git_source("git+git@host:me/x.git"),
+            Some(GitSource {
+                url: "git@host:me/x.git".to_string(),
+                reference: None,
+                subdirectory: None,
+            })
+        );
+        assert_eq!(parse_git_source("https://github.com/httpie/cli.git"), None);
+    }
+
+    #[test]
+    fn test_clone_git_source_checks_out_ref() {
+        let origin = tempdir().unwrap();
+        let repo = git2::Repository::init(origin.path()).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        let commit = |content: &str, message: &str| {
+            fs::write(origin.path().join("version.txt"), content).unwrap();
+            let mut index = repo.index().unwrap();
+            index.add_path(Path::new("version.txt")).unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let parents: Vec<git2::Commit> = repo
+                .head()
+                .ok()
+                .and_then(|head| head.peel_to_commit().ok())
+                .into_iter()
+                .collect();
+            let parents: Vec<&git2::Commit> = parents.iter().collect();
+            repo.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parents,
+            )
+            .unwrap()
+        };
+        let first = commit("1.0", "first");
+        repo.tag_lightweight("v1.0", &repo.find_object(first, None).unwrap(), false)
+            .unwrap();
+        commit("2.0", "second");
+
+        let url = Url::from_directory_path(origin.path()).unwrap().to_string();
+        let source = parse_git_source(&format!("git+{}@v1.0", url)).unwrap();
+        let checkout = tempdir().unwrap();
+        let dir = clone_git_source(&source, &checkout.path().join("checkout")).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("version.txt")).unwrap(), "1.0");
+    }
+
+    #[test]
+    fn test_requirement_resolved_from_local_index() {
+        let index = tempdir().unwrap();
+        let project = index.path().join("my-tool");
+        fs::create_dir(&project).unwrap();
+        for file in [
+            "my_tool-1.0-py3-none-any.whl",
+            "my_tool-1.1-py3-none-any.whl",
+            "my_tool-1.1.tar.gz",
+            "my_tool-1.2-cp312-cp312-manylinux_2_17_x86_64.whl",
+            "my_tool-2.0b1-py3-none-any.whl",
+        ] {
+            fs::write(project.join(file), file).unwrap();
+        }
+
+        let source = resolve_embed_source(
+            "My_Tool[cli]<2",
+            &index.path().to_string_lossy(),
+            Path::new("uv"),
+            &ToolOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            source.path.file_name().unwrap(),
+            "my_tool-1.1-py3-none-any.whl"
+        );
+        assert_eq!(
+            fs::read_to_string(&source.path).unwrap(),
+            "my_tool-1.1-py3-none-any.whl"
+        );
+        assert_eq!(source.extras, ["cli"]);
+
+        let err = resolve_embed_source(
+            "my-tool>=3",
+            &index.path().to_string_lossy(),
+            Path::new("uv"),
+            &ToolOptions::default(),
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("matches the requirement"));
+        assert!(
+            resolve_embed_source(
+                "./missing-project",
+                DEFAULT_INDEX_URL,
+                Path::new("uv"),
+                &ToolOptions::default()
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_archive_specs_are_paths() {
+        assert!(is_sdist(Path::new("dist/my_tool-1.1.tar.gz")));
+        assert!(is_sdist(Path::new("my-tool-1.1.zip")));
+        assert!(!is_sdist(Path::new("backup.zip")));
+        assert!(!is_sdist(Path::new("my_tool-1.1-py3-none-any.whl")));
+
+        let dir = tempdir().unwrap();
+        let index = dir.path().to_string_lossy().into_owned();
+        let resolve = |spec: &Path| {
+            resolve_embed_source(
+                &spec.to_string_lossy(),
+                &index,
+                Path::new("uv"),
+                &ToolOptions::default(),
+            )
+            .err()
+            .unwrap()
+        };
+        for missing in [
+            "my_tool-1.1-py3-none-any.whl",
+            "my_tool-1.1.tar.gz",
+            "project.zip",
+        ] {
+            let err = resolve(Path::new(missing));
+            assert_eq!(err.kind(), io::ErrorKind::NotFound);
+            assert!(err.to_string().contains("does not exist"));
+        }
+
+        let archive = dir.path().join("backup.zip");
+        fs::write(&archive, "").unwrap();
+        assert!(resolve(&archive).to_string().contains("is not an sdist"));
+
+        let readme = dir.path().join("README.md");
+        fs::write(&readme, "").unwrap();
+        let err = resolve(&readme);
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("neither a project directory"));
+
+        let wheel = dir.path().join("my_tool-1.1-py3-none-any.whl");
+        fs::write(&wheel, "").unwrap();
+        let source = resolve_embed_source(
+            &wheel.to_string_lossy(),
+            &index,
+            Path::new("uv"),
+            &ToolOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(source.path, wheel);
+    }
+
+    #[test]
+    fn test_select_release_skips_python2_wheels() {
+        let files = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| IndexFile {
+                    file_name: name.to_string(),
+                    location: name.to_string(),
+                    sha256: None,
+                })
+                .collect()
+        };
+        let requirement = Requirement::parse("futures").unwrap();
+        let release = select_release(
+            &requirement,
+            files(&[
+                "futures-3.3.0-py3-none-any.whl",
+                "futures-3.4.0-py2-none-any.whl",
+                "futures-3.4.0.tar.gz",
+            ]),
+        )
+        .unwrap();
+        assert_eq!(release.file_name, "futures-3.4.0.tar.gz");
+
+        let release = select_release(
+            &requirement,
+            files(&["futures-3.4.0-py2-none-any.whl", "futures-3.3.0.tar.gz"]),
+        )
+        .unwrap();
+        assert_eq!(release.file_name, "futures-3.3.0.tar.gz");
+    }
+
+    #[test]
+    fn test_parse_simple_page() {
+        let page = Url::parse("https://example.com/simple/my-tool/").unwrap();
+        let html = r#"<html><body>
+            <a href="../../packages/my_tool-1.0-py3-none-any.whl#sha256=abc">my_tool-1.0-py3-none-any.whl</a>
+            <a href="https://files.example.com/my_tool-0.9.tar.gz" data-yanked="broken">my_tool-0.9.tar.gz</a>
+        </body></html>"#;
+        let files = parse_simple_page(html, &page);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].file_name, "my_tool-1.0-py3-none-any.whl");
+        assert_eq!(
+            files[0].location,
+            "https://example.com/packages/my_tool-1.0-py3-none-any.whl"
+        );
+        assert_eq!(files[0].sha256.as_deref(), Some("abc"));
+    }
+}
@@ -1,7 +1,12 @@
 mod cli;
+mod config_command;
+mod fetch;
 mod payload;
 mod project;
+mod references;
+mod requirement;
 mod runner;
+mod validate;
 
 use clap::Parser;
 use cli::Cli;
@@ -16,26 +21,178 @@ pub struct CLIOptions {
     source_dir: PathBuf,
     output_path: PathBuf,
     uv_path: PathBuf,
-    uv_version: String,
+    uv_version: Option<String>,
+    config_path: Option<PathBuf>,
+    profile: Option<String>,
+    system_uv: Option<config::SystemUvPolicy>,
+    console_script: Option<String>,
+    extras: Vec<String>,
+    wheels: Vec<PathBuf>,
     no_uv_embed: bool,
     extract_to_temp: bool,
     delete_after_run: bool,
     force_uv_download: bool,
+    proxy: Option<String>,
+    ca_bundle: Option<String>,
+    uv_mirror: Option<String>,
+    uv_sha256: Option<String>,
     debug: bool,
 }
 
+// Command line flags form the highest configuration layer and are embedded like any other value
+fn cli_overrides(cli_options: &CLIOptions) -> toml::Table {
+    let mut options = toml::Table::new();
+    if cli_options.debug {
+        options.insert("debug".into(), true.into());
+    }
+    if cli_options.extract_to_temp {
+        options.insert("extract_to_temp".into(), true.into());
+    }
+    if cli_options.delete_after_run {
+        options.insert("delete_after_run".into(), true.into());
+    }
+    if let Some(uv_version) = &cli_options.uv_version {
+        options.insert("uv_version".into(), uv_version.clone().into());
+    }
+
+    let mut wheel = toml::Table::new();
+    if !cli_options.extras.is_empty() {
+        wheel.insert("extras".into(), cli_options.extras.clone().into());
+    }
+    if !cli_options.wheels.is_empty() {
+        let wheels: Vec<String> = cli_options
+            .wheels
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+        wheel.insert("wheels".into(), wheels.into());
+    }
+
+    let mut overrides = toml::Table::new();
+    if !options.is_empty() {
+        overrides.insert("options".into(), options.into());
+    }
+    if !wheel.is_empty() {
+        overrides.insert("wheel".into(), wheel.into());
+    }
+    if let Some(name) = &cli_options.console_script {
+        let mut entrypoint = toml::Table::new();
+        entrypoint.insert("console_script".into(), name.clone().into());
+        overrides.insert("entrypoint".into(), entrypoint.into());
+    }
+    overrides
+}
+
+fn load_config(cli_options: &CLIOptions) -> config::ProjectConfig {
+    // Defaults, [tool.pycrucible], pycrucible.toml, --config, --profile and CLI flags, in that order
+    let layered = config::load_layered_config(
+        &cli_options.source_dir,
+        cli_options.config_path.as_deref(),
+        cli_options.profile.as_deref(),
+        cli_overrides(cli_options),
+    );
+    match layered {
+        Ok(layered) => layered.config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Proxy and CA settings for fetching `--embed`. Sdists, git URLs and requirements are no project
+// directory yet, so only `--config`, `--profile` and the CLI flags apply to them.
+fn fetch_config(cli_options: &CLIOptions) -> config::ProjectConfig {
+    if cli_options.source_dir.is_dir() {
+        return load_config(cli_options);
+    }
+    let load = |profile| {
+        config::load_layered_config(
+            &cli_options.source_dir,
+            cli_options.config_path.as_deref(),
+            profile,
+            cli_overrides(cli_options),
+        )
+    };
+    // The profile may only be defined by the fetched project, it is checked again when building
+    match load(cli_options.profile.as_deref()).or_else(|_| load(None)) {
+        Ok(layered) => layered.config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// `--list-files`: what `embed_source` would collect, one line per file with the reason
+fn list_files(cli_options: &CLIOptions) -> io::Result<()> {
+    let project_config = load_config(cli_options);
+    if !cli_options.source_dir.is_dir() {
+        if let project::CollectedSources::Wheel { app, dependencies } =
+            project::collect_source_files(&cli_options.source_dir, &project_config)?
+        {
+            println!("+ {}  app wheel", app.absolute_path.display());
+            for wheel in dependencies {
+                println!("+ {}  wheel.wheels", wheel.absolute_path.display());
+            }
+        }
+        return Ok(());
+    }
+    let decisions = project::explain_source_files(&cli_options.source_dir, &project_config)?;
+    let output_path = cli_options.output_path.canonicalize().ok();
+    let width = decisions
+        .iter()
+        .map(|decision| decision.relative_path.len())
+        .max()
+        .unwrap_or(0);
+    for decision in decisions {
+        let (included, reason) = if Some(&decision.absolute_path) == output_path.as_ref() {
+            (false, "the output binary".to_string())
+        } else {
+            (decision.included, decision.reason)
+        };
+        println!(
+            "{} {:<width$}  {}",
+            if included { "+" } else { "-" },
+            decision.relative_path,
+            reason
+        );
+    }
+    Ok(())
+}
+
 fn embed_source(cli_options: CLIOptions) -> io::Result<()> {
-    // Create ProjectConfig based on pycrucible-toml or default if there is no such file
-    let mut project_config = config::load_project_config(&cli_options.source_dir.to_path_buf());
+    let mut project_config = load_config(&cli_options);
     debug_println!("[main.embed_source] - Project config: {:?}", project_config);
 
-    let sp = create_spinner_with_message("Collecting source files ...");
+    let mut sp = create_spinner_with_message("Collecting source files ...");
 
-    let collected_sources = project::collect_source_files(&cli_options.source_dir)?;
+    let mut collected_sources =
+        project::collect_source_files(&cli_options.source_dir, &project_config)?;
+    // A launcher built into the project directory earlier must not be embedded into the next one
+    if let (project::CollectedSources::Files(files), Ok(output_path)) = (
+        &mut collected_sources,
+        cli_options.output_path.canonicalize(),
+    ) {
+        files.retain(|file| file.absolute_path != output_path);
+    }
+    let manifest_path = payload::find_manifest_file(&cli_options.source_dir);
+
+    // Catch configuration mistakes here rather than on the end user's machine
+    if let Err(e) = validate::validate_build(
+        &cli_options.source_dir,
+        &project_config,
+        &collected_sources,
+        manifest_path.as_deref(),
+    ) {
+        sp.stop_and_persist("✖", "Source files are not valid for embedding.".into());
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 
     payload::embed_payload(
         &collected_sources,
-        &payload::find_manifest_file(&cli_options.source_dir),
+        &manifest_path,
         &mut project_config,
         cli_options,
     )?;
@@ -48,19 +205,23 @@ fn main() -> io::Result<()> {
     let cli = Cli::parse();
     debuging::set_debug_mode(cli.debug);
 
+    if let Some(cli::Command::Config(command)) = cli.command {
+        if let Err(e) = config_command::run(command) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     // Determine where we are running from, payload path and output path
     let current_dir = std::env::current_dir()?;
 
-    let payload_path: PathBuf = cli.embed;
-    if !payload_path.exists() {
-        eprintln!(
-            "The specified payload directory does not exist: {}",
-            payload_path.display()
-        );
-        std::process::exit(1);
-    }
+    // Required by clap unless a subcommand is given
+    let payload_spec = cli.embed.expect("--embed is required");
 
-    let output_path = if cli.output.is_none() {
+    let output_path = if let Some(output) = cli.output {
+        current_dir.join(output)
+    } else {
         let launcher_name = if cfg!(windows) {
             "launcher.exe"
         } else {
@@ -68,8 +229,6 @@ fn main() -> io::Result<()> {
         };
 
         current_dir.join(launcher_name)
-    } else {
-        current_dir.join(cli.output.unwrap())
     };
 
     // // Determine manifest file - this file contains requirements for the project
@@ -85,17 +244,56 @@ fn main() -> io::Result<()> {
     //     manifest_path
     // );
 
-    let cli_options = CLIOptions {
-        source_dir: payload_path.clone(),
+    let mut cli_options = CLIOptions {
+        source_dir: payload_spec.clone(),
         output_path: output_path.clone(),
         uv_path: cli.uv_path,
         uv_version: cli.uv_version,
+        config_path: cli.config,
+        profile: cli.profile,
+        system_uv: cli.system_uv,
+        console_script: cli.console_script,
+        extras: cli.extras,
+        // Relative to where the builder runs rather than to the app wheel
+        wheels: cli
+            .wheels
+            .iter()
+            .map(|path| current_dir.join(path))
+            .collect(),
         no_uv_embed: cli.no_uv_embed,
         extract_to_temp: cli.extract_to_temp,
         delete_after_run: cli.delete_after_run,
         force_uv_download: cli.force_uv_download,
+        proxy: cli.proxy,
+        ca_bundle: cli.ca_bundle,
+        uv_mirror: cli.uv_mirror,
+        uv_sha256: cli.uv_sha256,
         debug: cli.debug,
     };
+
+    // Sdists, git URLs and requirements are turned into a wheel first
+    let download_options = payload::uv_download_options(&cli_options, &fetch_config(&cli_options));
+    let source = match fetch::resolve_embed_source(
+        &payload_spec.to_string_lossy(),
+        &cli.index_url,
+        &cli_options.uv_path,
+        &download_options,
+    ) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    cli_options.source_dir = source.path.clone();
+    if cli_options.extras.is_empty() {
+        cli_options.extras = source.extras.clone();
+    }
+
+    if cli.list_files {
+        return list_files(&cli_options);
+    }
+
     // Embed the project and create new binary
     embed_source(cli_options)?;
     println!(
@@ -8,6 +8,7 @@ use std::fs::{self, OpenOptions};
 use std::io::Read;
 use std::io::{self, Cursor, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use zip::ZipArchive;
 use zip::{ZipWriter, write::FileOptions};
 
@@ -31,13 +32,114 @@ pub fn find_manifest_file(source_dir: &Path) -> Option<PathBuf> {
     }
 }
 
+const LOCKFILE: &str = "uv.lock";
+
+fn generate_lockfile(uv_path: &Path, source_dir: &Path) -> io::Result<()> {
+    println!(
+        "No {} found, generating one in {}",
+        LOCKFILE,
+        source_dir.display()
+    );
+    let status = Command::new(uv_path)
+        .arg("lock")
+        .arg("--project")
+        .arg(source_dir)
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "`uv lock` failed for {}",
+            source_dir.display()
+        )));
+    }
+    Ok(())
+}
+
+// Embeds `uv.lock` next to the manifest so every user installs the versions the project was
+// built with. A missing lockfile is generated for pyproject.toml projects.
+fn embed_lockfile(
+    cli_options: &crate::CLIOptions,
+    download_options: &config::ToolOptions,
+    manifest_path: &Path,
+    source_files: &[PathBuf],
+    zip: &mut ZipWriter<&mut Cursor<Vec<u8>>>,
+    options: FileOptions<'_, ()>,
+) -> io::Result<()> {
+    if download_options.lock_mode == config::LockMode::Off {
+        debug_println!("[payload.embed_lockfile] - lock_mode is off, not embedding a lockfile");
+        return Ok(());
+    }
+
+    let source_dir = manifest_path.parent().unwrap().canonicalize()?;
+    let lock_path = source_dir.join(LOCKFILE);
+    if !lock_path.exists() {
+        if manifest_path.file_name().and_then(|s| s.to_str()) != Some("pyproject.toml") {
+            eprintln!(
+                "No {} found and one can only be generated for pyproject.toml projects. Dependencies will be resolved on first run.",
+                LOCKFILE
+            );
+            return Ok(());
+        }
+        let uv_path = find_or_download_uv(Some(cli_options.uv_path.clone()), download_options)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("Could not find or download uv to generate {}", LOCKFILE),
+                )
+            })?;
+        generate_lockfile(&uv_path, &source_dir)?;
+    }
+
+    // Already part of the payload if the include patterns matched it
+    if source_files.contains(&lock_path) {
+        debug_println!("[payload.embed_lockfile] - Lockfile already collected");
+        return Ok(());
+    }
+    write_to_zip(LOCKFILE, lock_path, zip, options)?;
+    debug_println!("[payload.embed_lockfile] - Copied lockfile");
+    Ok(())
+}
+
+// Download settings from the CLI apply to this build only and are not embedded,
+// the runner uses whatever is set in the project configuration.
+pub fn uv_download_options(
+    cli_options: &crate::CLIOptions,
+    project_config: &config::ProjectConfig,
+) -> config::ToolOptions {
+    let mut options = project_config.options.clone();
+    if let Some(system_uv) = cli_options.system_uv {
+        options.system_uv = system_uv;
+    }
+    if cli_options.proxy.is_some() {
+        options.proxy = cli_options.proxy.clone();
+    }
+    if cli_options.ca_bundle.is_some() {
+        options.ca_bundle = cli_options.ca_bundle.clone();
+    }
+    if cli_options.uv_mirror.is_some() {
+        options.uv_mirror = cli_options.uv_mirror.clone();
+    }
+    if let Some(sha256) = &cli_options.uv_sha256 {
+        match shared::uv_handler::target_triple() {
+            Ok(target) => {
+                options
+                    .uv_sha256
+                    .get_or_insert_with(Default::default)
+                    .insert(target, sha256.clone());
+            }
+            Err(e) => eprintln!("Ignoring `--uv-sha256`: {}", e),
+        }
+    }
+    options
+}
+
 fn embed_uv(
     cli_options: &crate::CLIOptions,
+    download_options: &config::ToolOptions,
     zip: &mut ZipWriter<&mut Cursor<Vec<u8>>>,
     options: FileOptions<'_, ()>,
 ) -> io::Result<Option<()>> {
     debug_println!("[payload.embed_uv] - Embedding uv binary into payload");
-    let uv_path = find_or_download_uv(Some(cli_options.uv_path.clone()), &cli_options.uv_version);
+    let uv_path = find_or_download_uv(Some(cli_options.uv_path.clone()), download_options);
     match uv_path {
         None => {
             eprintln!("Could not find or download uv binary. uv will be required at runtime.");
@@ -74,27 +176,149 @@ fn write_to_zip(
     Ok(())
 }
 
-fn read_wheel_name(path: &str) -> Result<String, Box<dyn std::error::Error>> {
+/// What the builder needs from the `.dist-info` of a wheel.
+#[derive(Debug, Default)]
+struct WheelMetadata {
+    name: String,
+    console_scripts: Vec<String>,
+}
+
+// Names declared in the `[console_scripts]` section of `entry_points.txt`
+fn parse_console_scripts(entry_points: &str) -> Vec<String> {
+    let mut section = "";
+    let mut scripts = Vec::new();
+    for line in entry_points.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.trim();
+        } else if section == "console_scripts"
+            && let Some((name, _)) = line.split_once('=')
+        {
+            scripts.push(name.trim().to_string());
+        }
+    }
+    scripts
+}
+
+fn read_wheel_metadata(path: &Path) -> Result<WheelMetadata, Box<dyn std::error::Error>> {
     let file = File::open(path)?;
     let mut zip = ZipArchive::new(file)?;
+    let mut metadata = WheelMetadata::default();
 
     for i in 0..zip.len() {
         let mut entry = zip.by_index(i)?;
-        let name = entry.name();
-
-        if name.ends_with(".dist-info/METADATA") {
-            let mut contents = String::new();
-            entry.read_to_string(&mut contents)?;
+        let name = entry.name().to_string();
+        // Only the top level `.dist-info` describes the wheel itself
+        let Some((dist_info, file_name)) = name.split_once('/') else {
+            continue;
+        };
+        if !dist_info.ends_with(".dist-info") {
+            continue;
+        }
 
-            for line in contents.lines() {
-                if let Some(value) = line.strip_prefix("Name: ") {
-                    return Ok(value.trim().to_string());
+        let mut contents = String::new();
+        match file_name {
+            "METADATA" => {
+                entry.read_to_string(&mut contents)?;
+                if let Some(value) = contents.lines().find_map(|l| l.strip_prefix("Name: ")) {
+                    metadata.name = value.trim().to_string();
                 }
             }
+            "entry_points.txt" => {
+                entry.read_to_string(&mut contents)?;
+                metadata.console_scripts = parse_console_scripts(&contents);
+            }
+            _ => {}
         }
     }
 
-    Err("No Name field found in METADATA".into())
+    if metadata.name.is_empty() {
+        return Err("No Name field found in METADATA".into());
+    }
+    Ok(metadata)
+}
+
+fn console_script(name: &str) -> config::EntrypointSpec {
+    config::EntrypointSpec::Entry(config::EntrypointEntry {
+        console_script: Some(name.to_string()),
+        ..Default::default()
+    })
+}
+
+/// Points the entrypoints of a wheel build at its console scripts. A chosen console script must
+/// exist, otherwise the only one, or the one named after the distribution, is picked. Modules and
+/// callables are kept as they are.
+fn select_console_script(
+    package: &mut config::PackageConfig,
+    metadata: &WheelMetadata,
+) -> Result<(), String> {
+    let scripts = &metadata.console_scripts;
+    let check = |what: &str, name: &str| {
+        if scripts.iter().any(|script| script == name) {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} `{}` is not a console script of {} (available: {})",
+                what,
+                name,
+                metadata.name,
+                if scripts.is_empty() {
+                    "none".to_string()
+                } else {
+                    scripts.join(", ")
+                }
+            ))
+        }
+    };
+    for (name, entrypoint) in &package.entrypoints {
+        if let Ok(config::EntrypointKind::ConsoleScript(script)) = entrypoint.kind() {
+            check(&format!("entrypoints.{}", name), script)?;
+        }
+    }
+
+    let Some(default) = package.default_entrypoint() else {
+        return Ok(());
+    };
+    match default.kind() {
+        Ok(config::EntrypointKind::ConsoleScript(script)) => check("entrypoint", script),
+        Ok(config::EntrypointKind::Module(_) | config::EntrypointKind::Callable { .. }) => Ok(()),
+        // A file path (like the `main.py` default) cannot point into a wheel
+        _ => {
+            let normalized = |name: &str| name.to_lowercase().replace(['_', '.'], "-");
+            let picked = match scripts.as_slice() {
+                [only] => only,
+                [] => {
+                    return Err(format!(
+                        "{} declares no console scripts, set `entrypoint` to a `module` or `callable`",
+                        metadata.name
+                    ));
+                }
+                _ => scripts
+                    .iter()
+                    .find(|script| normalized(script) == normalized(&metadata.name))
+                    .ok_or_else(|| {
+                        format!(
+                            "{} declares several console scripts ({}), pick one with --console-script",
+                            metadata.name,
+                            scripts.join(", ")
+                        )
+                    })?,
+            };
+            debug_println!(
+                "[payload.select_console_script] - Using console script {}",
+                picked
+            );
+            package.entrypoint = console_script(picked);
+            Ok(())
+        }
+    }
+}
+
+fn wheel_file_name(wheel: &project::SourceFile) -> io::Result<&str> {
+    wheel
+        .absolute_path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid wheel file name"))
 }
 
 pub fn embed_payload(
@@ -106,46 +330,75 @@ pub fn embed_payload(
     runner::extract_runner(&cli_options.output_path)?;
     debug_println!("[payload.embed_payload] - Runner extracted to output path");
 
+    // Do not leave a runner without a payload behind
+    let result = write_payload(source_files, manifest_path, project_config, &cli_options);
+    if result.is_err() {
+        let _ = fs::remove_file(&cli_options.output_path);
+    }
+    result
+}
+
+fn write_payload(
+    source_files: &project::CollectedSources,
+    manifest_path: &Option<PathBuf>,
+    project_config: &mut config::ProjectConfig,
+    cli_options: &crate::CLIOptions,
+) -> io::Result<()> {
     // Create a memory buffer for the ZIP
     let mut cursor = Cursor::new(Vec::new());
     let mut zip: ZipWriter<&mut Cursor<Vec<u8>>> = ZipWriter::new(&mut cursor);
     let options: FileOptions<'_, ()> = FileOptions::<()>::default();
 
-    project_config.options.uv_version = cli_options.uv_version.to_string();
+    let download_options = uv_download_options(cli_options, project_config);
 
     // Check to see if we have a wheel or source files and handle accordingly
     match source_files {
-        project::CollectedSources::Wheel(wheel) => {
-            // Update project config with CLI options as we do not use any other file to store these in wheel mode
-            project_config.options.debug = cli_options.debug;
-            project_config.options.extract_to_temp = cli_options.extract_to_temp;
-            project_config.options.delete_after_run = cli_options.delete_after_run;
-
-            let wheel_path = &wheel.absolute_path;
-            let wheel_file_name =
-                wheel_path
-                    .file_name()
-                    .and_then(|s| s.to_str())
-                    .ok_or_else(|| {
-                        io::Error::new(io::ErrorKind::InvalidInput, "Invalid wheel file name")
-                    })?;
-            project_config.package.entrypoint = read_wheel_name(wheel_path.to_str().unwrap())
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
-            debug_println!(
-                "[payload.embed_payload] - Embedding wheel file: {:?}",
-                wheel_file_name
-            );
-            write_to_zip(wheel_file_name, wheel_path.clone(), &mut zip, options)?;
-            debug_println!("[payload.embed_payload] - Wheel file added to zip");
+        project::CollectedSources::Wheel { app, dependencies } => {
+            let metadata = read_wheel_metadata(&app.absolute_path)
+                .map_err(|e| io::Error::other(e.to_string()))?;
+            select_console_script(&mut project_config.package, &metadata)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+            let mut embedded: Vec<String> = Vec::new();
+            for wheel in std::iter::once(app).chain(dependencies) {
+                let wheel_file_name = wheel_file_name(wheel)?;
+                if embedded.iter().any(|name| name == wheel_file_name) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("Wheel {} is embedded twice", wheel_file_name),
+                    ));
+                }
+                debug_println!(
+                    "[payload.embed_payload] - Embedding wheel file: {:?}",
+                    wheel_file_name
+                );
+                write_to_zip(
+                    wheel_file_name,
+                    wheel.absolute_path.clone(),
+                    &mut zip,
+                    options,
+                )?;
+                embedded.push(wheel_file_name.to_string());
+            }
+            debug_println!("[payload.embed_payload] - Wheel files added to zip");
+
+            // The runner finds the wheels by the names they were embedded as
+            let wheel_config = project_config.wheel.get_or_insert_default();
+            wheel_config.app = Some(embedded.remove(0));
+            wheel_config.wheels = embedded;
         }
         project::CollectedSources::Files(files) => {
             if let Some(manifest) = manifest_path {
-                copy_source_to_zip(
-                    &files
-                        .iter()
-                        .map(|sf| sf.absolute_path.clone())
-                        .collect::<Vec<_>>(),
+                let files = files
+                    .iter()
+                    .map(|sf| sf.absolute_path.clone())
+                    .collect::<Vec<_>>();
+                copy_source_to_zip(&files, manifest, &mut zip, options)?;
+                embed_lockfile(
+                    cli_options,
+                    &download_options,
                     manifest,
+                    &files,
                     &mut zip,
                     options,
                 )?;
@@ -158,7 +411,7 @@ pub fn embed_payload(
         }
     }
 
-    create_pycrucible_config_file(&project_config, &mut zip, options)?;
+    create_pycrucible_config_file(project_config, &mut zip, options)?;
 
     if cli_options.no_uv_embed {
         debug_println!("[payload.embed_payload] - Skipping uv embedding as per no_uv_embed flag");
@@ -172,10 +425,10 @@ pub fn embed_payload(
             } else {
                 None
             };
-            find_or_download_uv(uv_path, cli_options.uv_version.as_str());
+            find_or_download_uv(uv_path, &download_options);
         }
         debug_println!("[payload.embed_payload] - Looking for uv binary to embed");
-        if let Some(_path) = embed_uv(&cli_options, &mut zip, options)? {
+        if let Some(_path) = embed_uv(cli_options, &download_options, &mut zip, options)? {
             debug_println!("[payload.embed_payload] - uv binary embedded successfully");
         } else {
             eprintln!("Could not find or download uv binary. uv will be required at runtime.");
@@ -190,7 +443,7 @@ pub fn embed_payload(
     // Open output file in append mode (the copied executable)
     let mut file: fs::File = OpenOptions::new()
         .append(true)
-        .open(cli_options.output_path)?;
+        .open(&cli_options.output_path)?;
 
     // Get offset where payload will start
     let offset = file.seek(SeekFrom::End(0))?;
@@ -238,27 +491,26 @@ fn copy_source_to_zip(
             relative_path
         );
         let file_data = fs::read(source_file).map_err(|e| {
-            io::Error::new(
-                io::ErrorKind::Other,
-                format!(
-                    "Failed to read source file {}: {}",
-                    source_file.display(),
-                    e
-                ),
-            )
+            io::Error::other(format!(
+                "Failed to read source file {}: {}",
+                source_file.display(),
+                e
+            ))
         })?;
         zip.start_file(relative_path, options)?;
         io::copy(&mut &file_data[..], zip)?;
     }
+    // Already part of the payload if the include patterns matched it
+    if source_files.contains(&manifest_path.canonicalize()?) {
+        debug_println!("[payload.embed_payload] - Manifest file already collected");
+        return Ok(());
+    }
     let mut manifest_file = fs::File::open(manifest_path).map_err(|e| {
-        io::Error::new(
-            io::ErrorKind::Other,
-            format!(
-                "Failed to open manifest file {}: {}",
-                manifest_path.display(),
-                e
-            ),
-        )
+        io::Error::other(format!(
+            "Failed to open manifest file {}: {}",
+            manifest_path.display(),
+            e
+        ))
     })?;
     let manifest_file_name = manifest_path
         .file_name()
@@ -319,6 +571,213 @@ mod tests {
         Ok(())
     }
 
+    fn read_zip_names(exe_path: &Path) -> Vec<String> {
+        let mut file = File::open(exe_path).unwrap();
+        let mut footer = [0u8; FOOTER_SIZE];
+        file.seek(SeekFrom::End(-(FOOTER_SIZE as i64))).unwrap();
+        file.read_exact(&mut footer).unwrap();
+        let offset = u64::from_le_bytes(footer[0..8].try_into().unwrap());
+        file.seek(SeekFrom::Start(offset)).unwrap();
+        let mut payload_data = Vec::new();
+        file.read_to_end(&mut payload_data).unwrap();
+        payload_data.truncate(payload_data.len() - FOOTER_SIZE);
+        let archive = zip::ZipArchive::new(std::io::Cursor::new(payload_data)).unwrap();
+        archive.file_names().map(str::to_string).collect()
+    }
+
+    fn test_cli_options(source_dir: &Path, output_path: &Path) -> crate::CLIOptions {
+        crate::CLIOptions {
+            source_dir: source_dir.to_path_buf(),
+            output_path: output_path.to_path_buf(),
+            uv_path: source_dir.join("uv"),
+            uv_version: Some("0.9.21".to_string()),
+            config_path: None,
+            profile: None,
+            system_uv: None,
+            console_script: None,
+            extras: Vec::new(),
+            wheels: Vec::new(),
+            no_uv_embed: true,
+            extract_to_temp: false,
+            delete_after_run: false,
+            force_uv_download: false,
+            proxy: None,
+            ca_bundle: None,
+            uv_mirror: None,
+            uv_sha256: None,
+            debug: false,
+        }
+    }
+
+    fn write_wheel(path: &Path, entry_points: &str) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        let options: FileOptions<'_, ()> = FileOptions::default();
+        zip.start_file("my_app/__init__.py", options).unwrap();
+        zip.start_file("my_app-1.0.dist-info/METADATA", options)
+            .unwrap();
+        zip.write_all(b"Metadata-Version: 2.1\nName: my-app\nVersion: 1.0\n")
+            .unwrap();
+        zip.start_file("my_app-1.0.dist-info/entry_points.txt", options)
+            .unwrap();
+        zip.write_all(entry_points.as_bytes()).unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_select_console_script_from_wheel() {
+        let dir = tempdir().unwrap();
+        let wheel = dir.path().join("my_app-1.0-py3-none-any.whl");
+        write_wheel(
+            &wheel,
+            "[console_scripts]\nmy-app = my_app.cli:main\nmy-app-admin = my_app.admin:main\n\n[gui_scripts]\nmy-app-gui = my_app.gui:main\n",
+        );
+        let metadata = read_wheel_metadata(&wheel).unwrap();
+        assert_eq!(metadata.name, "my-app");
+        assert_eq!(metadata.console_scripts, ["my-app", "my-app-admin"]);
+
+        // The default `main.py` picks the script named after the distribution
+        let mut package = config::PackageConfig::default();
+        select_console_script(&mut package, &metadata).unwrap();
+        assert_eq!(package.entrypoint, console_script("my-app"));
+
+        package.entrypoint = console_script("my-app-admin");
+        select_console_script(&mut package, &metadata).unwrap();
+        package.entrypoint = console_script("missing");
+        let err = select_console_script(&mut package, &metadata).unwrap_err();
+        assert!(err.contains("available: my-app, my-app-admin"));
+
+        let several = WheelMetadata {
+            name: "suite".to_string(),
+            console_scripts: vec!["serve".to_string(), "migrate".to_string()],
+        };
+        package.entrypoint = "main.py".into();
+        let err = select_console_script(&mut package, &several).unwrap_err();
+        assert!(err.contains("pick one with --console-script"));
+    }
+
+    #[test]
+    fn test_embed_payload_includes_lockfile() {
+        let dir = tempdir().unwrap();
+        let src_dir = dir.path().join("project");
+        fs::create_dir(&src_dir).unwrap();
+        fs::write(src_dir.join("main.py"), b"print('hello')").unwrap();
+        fs::write(
+            src_dir.join("pyproject.toml"),
+            b"[project]\nname = \"app\"\n",
+        )
+        .unwrap();
+        fs::write(src_dir.join("uv.lock"), b"version = 1\n").unwrap();
+
+        let output_path = dir.path().join("output_exe");
+
+        let source_files = project::CollectedSources::Files(vec![project::SourceFile {
+            absolute_path: src_dir.join("main.py").canonicalize().unwrap(),
+        }]);
+        let mut project_config = config::ProjectConfig::default();
+        embed_payload(
+            &source_files,
+            &Some(src_dir.join("pyproject.toml")),
+            &mut project_config,
+            test_cli_options(&src_dir, &output_path),
+        )
+        .unwrap();
+
+        let names = read_zip_names(&output_path);
+        assert!(names.contains(&"pyproject.toml".to_string()));
+        assert!(names.contains(&"uv.lock".to_string()));
+    }
+
+    #[test]
+    fn test_embed_payload_with_default_patterns() {
+        let dir = tempdir().unwrap();
+        let src_dir = dir.path().join("project");
+        fs::create_dir(&src_dir).unwrap();
+        fs::write(src_dir.join("main.py"), b"print('hello')").unwrap();
+        fs::write(
+            src_dir.join("pyproject.toml"),
+            b"[project]\nname = \"app\"\n",
+        )
+        .unwrap();
+        fs::write(src_dir.join("uv.lock"), b"version = 1\n").unwrap();
+        fs::write(
+            src_dir.join("pycrucible.toml"),
+            b"entrypoint = \"main.py\"\n",
+        )
+        .unwrap();
+
+        let output_path = dir.path().join("output_exe");
+        let mut project_config = config::ProjectConfig::default();
+        let source_files = project::collect_source_files(&src_dir, &project_config).unwrap();
+        embed_payload(
+            &source_files,
+            &find_manifest_file(&src_dir),
+            &mut project_config,
+            test_cli_options(&src_dir, &output_path),
+        )
+        .unwrap();
+
+        let mut names = read_zip_names(&output_path);
+        names.sort();
+        assert_eq!(
+            names,
+            ["main.py", "pycrucible.toml", "pyproject.toml", "uv.lock"]
+        );
+    }
+
+    #[test]
+    fn test_embed_payload_removes_output_on_failure() {
+        let dir = tempdir().unwrap();
+        let src_dir = dir.path().join("project");
+        fs::create_dir(&src_dir).unwrap();
+        fs::write(src_dir.join("requirements.txt"), b"requests").unwrap();
+
+        let output_path = dir.path().join("output_exe");
+        let source_files = project::CollectedSources::Files(vec![project::SourceFile {
+            absolute_path: src_dir.join("missing.py"),
+        }]);
+        let err = embed_payload(
+            &source_files,
+            &Some(src_dir.join("requirements.txt")),
+            &mut config::ProjectConfig::default(),
+            test_cli_options(&src_dir, &output_path),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("missing.py"));
+        assert!(!output_path.exists());
+    }
+
+    #[test]
+    fn test_embed_payload_skips_lockfile_when_lock_mode_off() {
+        let dir = tempdir().unwrap();
+        let src_dir = dir.path().join("project");
+        fs::create_dir(&src_dir).unwrap();
+        fs::write(src_dir.join("main.py"), b"print('hello')").unwrap();
+        fs::write(
+            src_dir.join("pyproject.toml"),
+            b"[project]\nname = \"app\"\n",
+        )
+        .unwrap();
+        fs::write(src_dir.join("uv.lock"), b"version = 1\n").unwrap();
+
+        let output_path = dir.path().join("output_exe");
+
+        let source_files = project::CollectedSources::Files(vec![project::SourceFile {
+            absolute_path: src_dir.join("main.py").canonicalize().unwrap(),
+        }]);
+        let mut project_config = config::ProjectConfig::default();
+        project_config.options.lock_mode = config::LockMode::Off;
+        embed_payload(
+            &source_files,
+            &Some(src_dir.join("pyproject.toml")),
+            &mut project_config,
+            test_cli_options(&src_dir, &output_path),
+        )
+        .unwrap();
+
+        let names = read_zip_names(&output_path);
+        assert!(!names.contains(&"uv.lock".to_string()));
+    }
+
     #[test]
     fn test_embed_and_extract_payload() {
         let dir = tempdir().unwrap();
@@ -334,7 +793,7 @@ mod tests {
 
         let mut project_config = config::ProjectConfig {
             package: config::PackageConfig {
-                entrypoint: "src/main.py".to_string(),
+                entrypoint: "src/main.py".into(),
                 ..Default::default()
             },
             options: config::ToolOptions {
@@ -370,11 +829,22 @@ mod tests {
             source_dir: src_dir.clone(),
             output_path: output_path.clone(),
             uv_path: uv_path.clone(),
-            uv_version: "0.9.21".to_string(),
+            uv_version: Some("0.9.21".to_string()),
+            config_path: None,
+            profile: None,
+            // The stub uv binary cannot report its version
+            system_uv: Some(config::SystemUvPolicy::Any),
+            console_script: None,
+            extras: Vec::new(),
+            wheels: Vec::new(),
             no_uv_embed: false,
             extract_to_temp: true,
             delete_after_run: false,
             force_uv_download: false,
+            proxy: None,
+            ca_bundle: None,
+            uv_mirror: None,
+            uv_sha256: None,
             debug: false,
         };
 
@@ -414,5 +884,6 @@ mod tests {
         assert!(extract_dir.join("src/utils.py").exists());
         assert!(extract_dir.join("requirements.txt").exists());
         assert!(extract_dir.join("pycrucible.toml").exists());
+        assert_eq!(fs::read(extract_dir.join("uv")).unwrap(), b"uv-binary");
     }
 }
@@ -1,11 +1,12 @@
 #![cfg_attr(test, allow(dead_code, unused_variables, unused_imports))]
 
 use glob::Pattern;
-use std::collections::HashSet;
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use std::io::{self, Error};
 use std::path::{Path, PathBuf};
 
-use crate::config::{ProjectConfig, load_project_config};
+use crate::config::ProjectConfig;
 use crate::debug_println;
 
 #[derive(Debug)]
@@ -14,109 +15,465 @@ pub struct SourceFile {
 }
 
 pub enum CollectedSources {
-    Wheel(SourceFile),
+    // The app wheel and the additional wheels from `[wheel]`
+    Wheel {
+        app: SourceFile,
+        dependencies: Vec<SourceFile>,
+    },
     Files(Vec<SourceFile>),
 }
 
-fn should_include_file(
-    file_path: &Path,
-    source_dir: &Path,
+// The include pattern `relative_path` matches, or why it is left out. Excludes are checked first.
+fn match_patterns(
+    relative_path: &str,
     include_patterns: &[String],
     exclude_patterns: &[String],
-) -> bool {
-    let relative_path = file_path
-        .strip_prefix(source_dir)
-        .unwrap_or_else(|_| {
-            eprintln!(
-                "[project.should_include_file] - Failed to strip prefix: file_path={}, source_dir={}",
-                file_path.display(), source_dir.display()
-            );
-            file_path
+) -> Result<String, String> {
+    if let Some(pattern) = exclude_patterns
+        .iter()
+        .find(|pattern| Pattern::new(pattern).is_ok_and(|p| p.matches(relative_path)))
+    {
+        return Err(format!("matches patterns.exclude `{}`", pattern));
+    }
+    include_patterns
+        .iter()
+        .find(|pattern| Pattern::new(pattern).is_ok_and(|p| p.matches(relative_path)))
+        .map(|pattern| format!("matches patterns.include `{}`", pattern))
+        .ok_or_else(|| "matches no patterns.include".to_string())
+}
+
+/// Checks that every include/exclude pattern is a valid glob.
+pub fn validate_patterns(include: &[String], exclude: &[String]) -> Result<(), String> {
+    let invalid: Vec<String> = include
+        .iter()
+        .map(|p| ("include", p))
+        .chain(exclude.iter().map(|p| ("exclude", p)))
+        .filter_map(|(kind, pattern)| {
+            Pattern::new(pattern)
+                .err()
+                .map(|e| format!("invalid {} pattern '{}': {}", kind, pattern, e.msg))
         })
-        .to_string_lossy()
-        .replace("\\", "/");
-    // Check exclude patterns first
-    for pattern in exclude_patterns {
-        if Pattern::new(pattern).unwrap().matches(&relative_path) {
-            debug_println!(
-                "[project.should_include_file] - Excluded by pattern '{}' for file {}",
-                pattern,
-                file_path.display()
-            );
-            return false;
+        .collect();
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        Err(invalid.join("; "))
+    }
+}
+
+// Data files a pyproject.toml ships with the package: `[tool.setuptools.package-data]` and
+// hatch's `force-include`
+fn pyproject_resources(source_dir: &Path) -> Vec<String> {
+    let Some(doc) = std::fs::read_to_string(source_dir.join("pyproject.toml"))
+        .ok()
+        .and_then(|raw| toml::from_str::<toml::Value>(&raw).ok())
+    else {
+        return Vec::new();
+    };
+    let Some(tool) = doc.get("tool") else {
+        return Vec::new();
+    };
+    let mut resources = Vec::new();
+
+    let package_data = tool
+        .get("setuptools")
+        .and_then(|setuptools| setuptools.get("package-data"))
+        .and_then(|data| data.as_table());
+    for (package, patterns) in package_data.into_iter().flatten() {
+        // `""` and `"*"` apply to every package
+        let package_dir = if package.is_empty() || package == "*" {
+            "**".to_string()
+        } else {
+            let path = package.replace('.', "/");
+            if source_dir.join("src").join(&path).is_dir() {
+                format!("src/{}", path)
+            } else {
+                path
+            }
+        };
+        for pattern in patterns.as_array().into_iter().flatten() {
+            if let Some(pattern) = pattern.as_str() {
+                resources.push(format!("{}/{}", package_dir, pattern));
+            }
         }
     }
 
-    // If include patterns are specified, file must match at least one
-    let included = include_patterns
-        .iter()
-        .any(|pattern| Pattern::new(pattern).unwrap().matches(&relative_path));
-    if !included {
-        debug_println!(
-            "[project.should_include_file] - Not included by any pattern for file {}",
-            file_path.display()
-        );
+    let force_include = tool
+        .get("hatch")
+        .and_then(|hatch| hatch.get("build"))
+        .and_then(|build| build.get("targets"))
+        .and_then(|targets| targets.get("wheel"))
+        .and_then(|wheel| wheel.get("force-include"))
+        .and_then(|paths| paths.as_table());
+    for path in force_include.into_iter().flat_map(|paths| paths.keys()) {
+        // Paths outside the project are not part of the payload
+        if !Path::new(path).is_absolute() && !path.split('/').any(|part| part == "..") {
+            resources.push(path.trim_end_matches('/').to_string());
+        }
+    }
+
+    debug_println!(
+        "[project.pyproject_resources] - Resources from pyproject.toml: {:?}",
+        resources
+    );
+    resources
+}
+
+// A resource pattern naming a directory takes everything below it
+fn matches_resource(relative_path: &str, pattern: &Pattern) -> bool {
+    Path::new(relative_path)
+        .ancestors()
+        .filter(|ancestor| !ancestor.as_os_str().is_empty())
+        .any(|ancestor| pattern.matches(&ancestor.to_string_lossy().replace("\\", "/")))
+}
+
+// Ignore files in every directory of the project, highest precedence first
+const IGNORE_FILES: [&str; 3] = [".pycrucibleignore", ".ignore", ".gitignore"];
+
+// Applied below every ignore file, so a project without a `.gitignore` does not ship secrets,
+// virtual environments or build output. A `!` line in any ignore file re-includes a file.
+const DEFAULT_IGNORES: [&str; 10] = [
+    ".env*",
+    "/.venv/",
+    "/venv/",
+    "/env/",
+    "/dist/",
+    "/build/",
+    ".git/",
+    "__pycache__/",
+    "*.pyc",
+    "*.pyo",
+];
+
+/// Whether a file (or a skipped directory, ending with `/`) is embedded and why, see `--list-files`.
+#[derive(Debug)]
+pub struct FileDecision {
+    pub absolute_path: PathBuf,
+    pub relative_path: String,
+    pub included: bool,
+    pub reason: String,
+}
+
+// The ignore files of a directory being walked
+struct IgnoreLevel {
+    depth: usize,
+    matchers: [Option<Gitignore>; 3],
+    // Set when the directory itself is ignored, nothing below it can be un-ignored
+    ignored_by: Option<String>,
+}
+
+fn load_ignore_files(dir: &Path) -> [Option<Gitignore>; 3] {
+    IGNORE_FILES.map(|name| {
+        let path = dir.join(name);
+        if !path.is_file() {
+            return None;
+        }
+        let mut builder = GitignoreBuilder::new(dir);
+        if let Some(e) = builder.add(&path) {
+            eprintln!("Warning: {}: {}", path.display(), e);
+        }
+        builder
+            .build()
+            .inspect_err(|e| eprintln!("Warning: {}: {}", path.display(), e))
+            .ok()
+    })
+}
+
+fn default_ignores(source_dir: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(source_dir);
+    for line in DEFAULT_IGNORES {
+        builder
+            .add_line(None, line)
+            .expect("built-in ignore patterns are valid");
     }
-    included
+    builder.build().expect("built-in ignore patterns are valid")
 }
 
-pub fn collect_source_files_with_config(
+// Ignore files above the project count up to the root of the git repository it is in. Returned
+// outermost first, outside of a repository there are none.
+fn ancestor_ignore_levels(source_dir: &Path) -> Vec<IgnoreLevel> {
+    let Some(repo_root) = source_dir.ancestors().find(|dir| dir.join(".git").exists()) else {
+        return Vec::new();
+    };
+    let mut levels: Vec<IgnoreLevel> = source_dir
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(repo_root))
+        .map(|dir| IgnoreLevel {
+            depth: 0,
+            matchers: load_ignore_files(dir),
+            ignored_by: None,
+        })
+        .collect();
+    levels.reverse();
+    levels
+}
+
+// The ignore file a glob came from, relative to the project (`../.gitignore` above it)
+fn ignore_file_name(from: &Path, source_dir: &Path) -> String {
+    if let Ok(relative) = from.strip_prefix(source_dir) {
+        return relative.to_string_lossy().replace('\\', "/");
+    }
+    let Some(dir) = from.parent() else {
+        return from.display().to_string();
+    };
+    match source_dir.strip_prefix(dir) {
+        Ok(below) => format!(
+            "{}{}",
+            "../".repeat(below.components().count()),
+            from.file_name().unwrap_or_default().to_string_lossy()
+        ),
+        Err(_) => from.display().to_string(),
+    }
+}
+
+// `.pycrucibleignore` wins over `.ignore`, which wins over `.gitignore`, which wins over
+// `DEFAULT_IGNORES`; within one kind the deepest directory wins. `Some((true, _))` is ignored,
+// `Some((false, _))` re-included by `!`.
+fn ignore_match(
+    levels: &[IgnoreLevel],
+    defaults: &Gitignore,
+    path: &Path,
+    is_dir: bool,
+    source_dir: &Path,
+) -> Option<(bool, String)> {
+    let describe = |glob: &ignore::gitignore::Glob| {
+        let file = match glob.from() {
+            Some(from) => ignore_file_name(from, source_dir),
+            None => "the built-in defaults".to_string(),
+        };
+        (
+            !glob.is_whitelist(),
+            format!("`{}` in {}", glob.original(), file),
+        )
+    };
+    (0..IGNORE_FILES.len())
+        .find_map(|kind| {
+            levels.iter().rev().find_map(|level| {
+                match level.matchers[kind].as_ref()?.matched(path, is_dir) {
+                    Match::None => None,
+                    Match::Ignore(glob) | Match::Whitelist(glob) => Some(describe(glob)),
+                }
+            })
+        })
+        .or_else(|| match defaults.matched(path, is_dir) {
+            Match::None => None,
+            Match::Ignore(glob) | Match::Whitelist(glob) => Some(describe(glob)),
+        })
+}
+
+// `patterns.exclude` entries like `.venv/**/*` leave out a whole directory
+fn excluded_dir(relative_dir: &str, exclude_patterns: &[String]) -> Option<String> {
+    exclude_patterns
+        .iter()
+        .find(|pattern| {
+            pattern
+                .strip_suffix("/**/*")
+                .or_else(|| pattern.strip_suffix("/**"))
+                .and_then(|dir| Pattern::new(dir).ok())
+                .is_some_and(|dir| dir.matches(relative_dir))
+        })
+        .map(|pattern| format!("matches patterns.exclude `{}`", pattern))
+}
+
+// An ignored directory is only walked if a resource pattern could match below it
+fn may_contain_resource(relative_dir: &str, resources: &[String]) -> bool {
+    let dir = format!("{}/", relative_dir);
+    resources.iter().any(|resource| {
+        let literal = resource.split(['*', '?', '[']).next().unwrap_or_default();
+        literal.starts_with(&dir) || dir.starts_with(literal)
+    })
+}
+
+/// Decides for every file below `source_dir` whether it is embedded. Resources are always
+/// embedded, then `.pycrucibleignore`, `.ignore` and `.gitignore` files (with `!` negation, up to
+/// the repository root) and `DEFAULT_IGNORES` are applied, then `patterns.exclude` and
+/// `patterns.include`.
+pub fn explain_source_files(
     source_dir: &Path,
     project_config: &ProjectConfig,
-) -> io::Result<Vec<SourceFile>> {
+) -> io::Result<Vec<FileDecision>> {
     debug_println!(
-        "[project.collect_source_files] - Collecting source files from: {:?}",
+        "[project.explain_source_files] - Collecting source files from: {:?}",
         source_dir
     );
-    let mut files = Vec::new();
-    let mut seen_paths = HashSet::new();
     let source_dir = source_dir.canonicalize().map_err(|e| {
-        Error::new(
-            io::ErrorKind::Other,
-            format!(
-                "Failed to canonicalize source_dir {}: {}",
-                source_dir.display(),
-                e
-            ),
-        )
+        Error::other(format!(
+            "Failed to canonicalize source_dir {}: {}",
+            source_dir.display(),
+            e
+        ))
     })?;
 
     let include_patterns = &project_config.package.patterns.include;
     let exclude_patterns = &project_config.package.patterns.exclude;
+    validate_patterns(include_patterns, exclude_patterns)
+        .map_err(|e| Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    let mut resources = project_config.package.resources.clone();
+    resources.extend(pyproject_resources(&source_dir));
+    let resource_patterns =
+        compile_resources(&resources).map_err(|e| Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut resource_matched = vec![false; resources.len()];
 
-    for entry in walkdir::WalkDir::new(&source_dir)
+    let mut decisions = Vec::new();
+    let defaults = default_ignores(&source_dir);
+    let mut levels = ancestor_ignore_levels(&source_dir);
+    let mut walker = walkdir::WalkDir::new(&source_dir)
         .follow_links(true)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        if entry.file_type().is_file()
-            && should_include_file(
-                entry.path(),
-                &source_dir,
-                include_patterns,
-                exclude_patterns,
-            )
-        {
-            let absolute_path = entry.path().to_path_buf();
+        .sort_by_file_name()
+        .into_iter();
+    while let Some(entry) = walker.next() {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        // The project directory itself keeps the levels of the directories above it
+        if entry.depth() > 0 {
+            levels.retain(|level| level.depth < entry.depth());
+        }
+        let path = entry.path();
+        let relative_path = path
+            .strip_prefix(&source_dir)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let inherited = levels.last().and_then(|level| level.ignored_by.clone());
 
-            if seen_paths.contains(&absolute_path) {
+        if entry.file_type().is_dir() {
+            let ignored_by = if entry.depth() == 0 {
+                None
+            } else {
+                inherited
+                    .or_else(
+                        || match ignore_match(&levels, &defaults, path, true, &source_dir) {
+                            Some((true, glob)) => Some(format!("ignored by {}", glob)),
+                            _ => None,
+                        },
+                    )
+                    .or_else(|| excluded_dir(&relative_path, exclude_patterns))
+            };
+            if let Some(reason) = &ignored_by
+                && !may_contain_resource(&relative_path, &resources)
+            {
                 debug_println!(
-                    "[project.collect_source_files] - Skipping duplicate file: {}",
-                    absolute_path.display()
+                    "[project.explain_source_files] - Skipping {}: {}",
+                    relative_path,
+                    reason
                 );
+                decisions.push(FileDecision {
+                    absolute_path: path.to_path_buf(),
+                    relative_path: format!("{}/", relative_path),
+                    included: false,
+                    reason: reason.clone(),
+                });
+                walker.skip_current_dir();
                 continue;
             }
+            levels.push(IgnoreLevel {
+                depth: entry.depth(),
+                matchers: load_ignore_files(path),
+                ignored_by,
+            });
+            continue;
+        }
+        if !entry.file_type().is_file() {
+            continue;
+        }
 
-            debug_println!(
-                "[project.collect_source_files] - Collected file at path: {}",
-                absolute_path.display()
-            );
+        let mut resource = None;
+        for ((pattern, original), matched) in resource_patterns
+            .iter()
+            .zip(&resources)
+            .zip(resource_matched.iter_mut())
+        {
+            if matches_resource(&relative_path, pattern) {
+                *matched = true;
+                resource.get_or_insert(original);
+            }
+        }
+        // The embedded pycrucible.toml is generated from the merged configuration
+        let (included, reason) = if relative_path == "pycrucible.toml" {
+            (
+                false,
+                "replaced by the generated pycrucible.toml".to_string(),
+            )
+        } else if let Some(resource) = resource {
+            (true, format!("resource `{}`", resource))
+        } else if let Some(reason) = inherited {
+            (false, reason)
+        } else {
+            let ignored = ignore_match(&levels, &defaults, path, false, &source_dir);
+            match ignored {
+                Some((true, glob)) => (false, format!("ignored by {}", glob)),
+                _ => match match_patterns(&relative_path, include_patterns, exclude_patterns) {
+                    Ok(reason) => match ignored {
+                        Some((_, glob)) => (true, format!("{}, re-included by {}", reason, glob)),
+                        None => (true, reason),
+                    },
+                    Err(reason) => (false, reason),
+                },
+            }
+        };
+        debug_println!(
+            "[project.explain_source_files] - {} {}: {}",
+            if included { "Collected" } else { "Skipped" },
+            relative_path,
+            reason
+        );
+        decisions.push(FileDecision {
+            absolute_path: path.to_path_buf(),
+            relative_path,
+            included,
+            reason,
+        });
+    }
 
-            seen_paths.insert(absolute_path.clone());
-            files.push(SourceFile { absolute_path });
+    for (resource, matched) in resources.iter().zip(resource_matched) {
+        if !matched {
+            eprintln!(
+                "Warning: resource `{}` does not match any file in {}",
+                resource,
+                source_dir.display()
+            );
         }
     }
-    Ok(files)
+    Ok(decisions)
+}
+
+/// Compiles `resources` globs, reporting every invalid one at once.
+pub fn compile_resources(resources: &[String]) -> Result<Vec<Pattern>, String> {
+    let mut invalid = Vec::new();
+    let patterns = resources
+        .iter()
+        .filter_map(|resource| {
+            Pattern::new(resource)
+                .map_err(|e| {
+                    invalid.push(format!(
+                        "invalid resource pattern '{}': {}",
+                        resource, e.msg
+                    ))
+                })
+                .ok()
+        })
+        .collect();
+    if invalid.is_empty() {
+        Ok(patterns)
+    } else {
+        Err(invalid.join("; "))
+    }
+}
+
+pub fn collect_source_files_with_config(
+    source_dir: &Path,
+    project_config: &ProjectConfig,
+) -> io::Result<Vec<SourceFile>> {
+    Ok(explain_source_files(source_dir, project_config)?
+        .into_iter()
+        .filter(|decision| decision.included)
+        .map(|decision| SourceFile {
+            absolute_path: decision.absolute_path,
+        })
+        .collect())
 }
 
 fn collect_wheel(source_wheel: &Path) -> io::Result<SourceFile> {
@@ -127,14 +484,11 @@ fn collect_wheel(source_wheel: &Path) -> io::Result<SourceFile> {
 
     let mut wheel_package: Option<SourceFile> = None;
     let source_wheel = source_wheel.canonicalize().map_err(|e| {
-        Error::new(
-            io::ErrorKind::Other,
-            format!(
-                "Failed to canonicalize source_wheel {}: {}",
-                source_wheel.display(),
-                e
-            ),
-        )
+        Error::other(format!(
+            "Failed to canonicalize source_wheel {}: {}",
+            source_wheel.display(),
+            e
+        ))
     })?;
 
     if source_wheel.is_file()
@@ -154,7 +508,10 @@ fn collect_wheel(source_wheel: &Path) -> io::Result<SourceFile> {
     ))
 }
 
-pub fn collect_source_files(source_dir: &Path) -> io::Result<CollectedSources> {
+pub fn collect_source_files(
+    source_dir: &Path,
+    project_config: &ProjectConfig,
+) -> io::Result<CollectedSources> {
     let is_wheel = source_dir
         .extension()
         .and_then(|e| e.to_str())
@@ -162,10 +519,17 @@ pub fn collect_source_files(source_dir: &Path) -> io::Result<CollectedSources> {
         .unwrap_or(false);
 
     if is_wheel {
-        collect_wheel(source_dir).map(CollectedSources::Wheel)
+        let app = collect_wheel(source_dir)?;
+        let wheel_dir = app.absolute_path.parent().unwrap_or(Path::new("."));
+        let dependencies = project_config
+            .wheel
+            .iter()
+            .flat_map(|wheel| &wheel.wheels)
+            .map(|path| collect_wheel(&wheel_dir.join(path)))
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(CollectedSources::Wheel { app, dependencies })
     } else {
-        let config = load_project_config(&source_dir.to_path_buf());
-        collect_source_files_with_config(source_dir, &config).map(CollectedSources::Files)
+        collect_source_files_with_config(source_dir, project_config).map(CollectedSources::Files)
     }
 }
 
@@ -180,42 +544,28 @@ mod tests {
     fn mock_config_with_patterns() -> crate::config::ProjectConfig {
         crate::config::ProjectConfig {
             package: PackageConfig {
-                entrypoint: "main.py".to_string(),
+                entrypoint: "main.py".into(),
                 patterns: FilePatterns::default(),
+                ..Default::default()
             },
             ..Default::default()
         }
     }
 
     #[test]
-    fn test_should_include_file() {
-        let source_dir = PathBuf::from("/project");
-
-        let file_py = source_dir.join("main.py");
-        let file_txt = source_dir.join("README.txt");
-        let file_test = source_dir.join("tests/test_main.py");
-
+    fn test_match_patterns() {
         let include = vec!["**/*.py".to_string()];
         let exclude = vec!["tests/*".to_string()];
 
-        assert!(should_include_file(
-            &file_py,
-            &source_dir,
-            &include,
-            &exclude
-        ));
-        assert!(!should_include_file(
-            &file_txt,
-            &source_dir,
-            &include,
-            &exclude
-        ));
-        assert!(!should_include_file(
-            &file_test,
-            &source_dir,
-            &include,
-            &exclude
-        ));
+        assert_eq!(
+            match_patterns("main.py", &include, &exclude),
+            Ok("matches patterns.include `**/*.py`".to_string())
+        );
+        assert!(match_patterns("README.txt", &include, &exclude).is_err());
+        assert_eq!(
+            match_patterns("tests/test_main.py", &include, &exclude),
+            Err("matches patterns.exclude `tests/*`".to_string())
+        );
     }
 
     #[test]
@@ -232,11 +582,12 @@ mod tests {
 
         let mock_config = ProjectConfig {
             package: PackageConfig {
-                entrypoint: "main.py".to_string(),
+                entrypoint: "main.py".into(),
                 patterns: FilePatterns {
                     include: vec!["**/*.py".to_string()],
                     exclude: vec!["tests/*".to_string()],
                 },
+                ..Default::default()
             },
             ..Default::default()
         };
@@ -251,4 +602,321 @@ mod tests {
         assert_eq!(collected_paths.len(), 1);
         assert_eq!(collected_paths[0], PathBuf::from("src/main.py"));
     }
+
+    fn collected_paths(root: &Path, config: &ProjectConfig) -> Vec<String> {
+        let root = root.canonicalize().unwrap();
+        let mut paths: Vec<String> = collect_source_files_with_config(&root, config)
+            .unwrap()
+            .iter()
+            .map(|sf| {
+                sf.absolute_path
+                    .strip_prefix(&root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace("\\", "/")
+            })
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_default_patterns_honour_gitignore() {
+        let temp = tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("app/templates")).unwrap();
+        fs::create_dir_all(root.join("build")).unwrap();
+        fs::write(root.join("main.py"), b"").unwrap();
+        fs::write(root.join("app/py.typed"), b"").unwrap();
+        fs::write(root.join("app/templates/index.html"), b"").unwrap();
+        fs::write(root.join("app/secrets.env"), b"").unwrap();
+        fs::write(root.join("build/out.py"), b"").unwrap();
+        fs::write(root.join(".gitignore"), b"build/\n").unwrap();
+        fs::write(root.join("app/.gitignore"), b"*.env\n").unwrap();
+
+        assert_eq!(
+            collected_paths(root, &ProjectConfig::default()),
+            vec![
+                ".gitignore",
+                "app/.gitignore",
+                "app/py.typed",
+                "app/templates/index.html",
+                "main.py",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_default_ignores_without_gitignore() {
+        let temp = tempdir().unwrap();
+        let root = temp.path();
+        for dir in [
+            "app/__pycache__",
+            "app/build",
+            "venv/lib",
+            "env/bin",
+            "dist",
+            "build/lib",
+        ] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "main.py",
+            ".env",
+            ".env.production",
+            "app/.env.local",
+            "app/__init__.py",
+            "app/__pycache__/__init__.cpython-312.pyc",
+            "app/legacy.pyc",
+            "app/build/steps.py",
+            "venv/lib/site.py",
+            "env/bin/python",
+            "dist/app-1.0-py3-none-any.whl",
+            "build/lib/app.py",
+        ] {
+            fs::write(root.join(file), b"").unwrap();
+        }
+
+        // Nested `build` packages are only left out by a `.gitignore` saying so
+        assert_eq!(
+            collected_paths(root, &ProjectConfig::default()),
+            vec!["app/__init__.py", "app/build/steps.py", "main.py"]
+        );
+
+        fs::write(
+            root.join(".pycrucibleignore"),
+            b"!.env.production
+",
+        )
+        .unwrap();
+        let decisions = explain_source_files(root, &ProjectConfig::default()).unwrap();
+        let reason = |path: &str| {
+            decisions
+                .iter()
+                .find(|d| d.relative_path == path)
+                .map(|d| (d.included, d.reason.as_str()))
+                .unwrap()
+        };
+        assert_eq!(
+            reason(".env"),
+            (false, "ignored by `.env*` in the built-in defaults")
+        );
+        assert_eq!(
+            reason("venv/"),
+            (false, "ignored by `/venv/` in the built-in defaults")
+        );
+        assert!(reason(".env.production").0);
+    }
+
+    #[test]
+    fn test_ignore_files_up_to_the_repository_root() {
+        let temp = tempdir().unwrap();
+        let repo = temp.path();
+        let root = repo.join("apps/tool");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            repo.join(".gitignore"),
+            b"*.log
+/apps/tool/cache/
+",
+        )
+        .unwrap();
+        fs::write(
+            repo.join("apps/.ignore"),
+            b"*.sqlite
+",
+        )
+        .unwrap();
+        fs::write(root.join("main.py"), b"").unwrap();
+        fs::write(root.join("debug.log"), b"").unwrap();
+        fs::write(root.join("data.sqlite"), b"").unwrap();
+        fs::create_dir_all(root.join("cache")).unwrap();
+        fs::write(root.join("cache/entry.py"), b"").unwrap();
+
+        assert_eq!(
+            collected_paths(&root, &ProjectConfig::default()),
+            vec!["main.py"]
+        );
+        let decisions = explain_source_files(&root, &ProjectConfig::default()).unwrap();
+        let reason = |path: &str| {
+            decisions
+                .iter()
+                .find(|d| d.relative_path == path)
+                .map(|d| d.reason.clone())
+                .unwrap()
+        };
+        assert_eq!(
+            reason("debug.log"),
+            "ignored by `*.log` in ../../.gitignore"
+        );
+        assert_eq!(reason("data.sqlite"), "ignored by `*.sqlite` in ../.ignore");
+        assert_eq!(
+            reason("cache/"),
+            "ignored by `/apps/tool/cache/` in ../../.gitignore"
+        );
+
+        // Without a repository only the project's own ignore files count
+        fs::remove_dir(repo.join(".git")).unwrap();
+        assert_eq!(
+            collected_paths(&root, &ProjectConfig::default()),
+            vec!["cache/entry.py", "data.sqlite", "debug.log", "main.py"]
+        );
+    }
+
+    #[test]
+    fn test_ignore_files_precedence_and_negation() {
+        let temp = tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("app/keys")).unwrap();
+        fs::write(root.join("main.py"), b"").unwrap();
+        fs::write(root.join(".env"), b"").unwrap();
+        fs::write(root.join(".env.example"), b"").unwrap();
+        fs::write(root.join("notes.md"), b"").unwrap();
+        fs::write(root.join("app/keys/id.pem"), b"").unwrap();
+        fs::write(root.join("app/keys/public.pem"), b"").unwrap();
+        fs::write(root.join(".gitignore"), b".env*\n").unwrap();
+        fs::write(root.join(".ignore"), b"*.md\n").unwrap();
+        fs::write(root.join(".pycrucibleignore"), b"!.env.example\n.*ignore\n").unwrap();
+        fs::write(
+            root.join("app/keys/.pycrucibleignore"),
+            b"*.pem\n!public.pem\n",
+        )
+        .unwrap();
+
+        let config = ProjectConfig::default();
+        assert_eq!(
+            collected_paths(root, &config),
+            vec![".env.example", "app/keys/public.pem", "main.py"]
+        );
+
+        let decisions = explain_source_files(root, &config).unwrap();
+        let reason = |path: &str| {
+            decisions
+                .iter()
+                .find(|d| d.relative_path == path)
+                .map(|d| (d.included, d.reason.as_str()))
+                .unwrap()
+        };
+        assert_eq!(reason(".env"), (false, "ignored by `.env*` in .gitignore"));
+        assert_eq!(
+            reason(".env.example"),
+            (
+                true,
+                "matches patterns.include `**/*`, re-included by `!.env.example` in .pycrucibleignore"
+            )
+        );
+        assert_eq!(reason("notes.md"), (false, "ignored by `*.md` in .ignore"));
+        assert_eq!(
+            reason("app/keys/id.pem"),
+            (false, "ignored by `*.pem` in app/keys/.pycrucibleignore")
+        );
+        assert_eq!(reason("main.py"), (true, "matches patterns.include `**/*`"));
+    }
+
+    #[test]
+    fn test_excluded_directories_are_listed_once() {
+        let temp = tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join(".venv/lib")).unwrap();
+        fs::create_dir_all(root.join("dist")).unwrap();
+        fs::write(root.join("main.py"), b"").unwrap();
+        fs::write(root.join(".venv/lib/site.py"), b"").unwrap();
+        fs::write(root.join("dist/app.whl"), b"").unwrap();
+        fs::write(root.join(".gitignore"), b"dist/\n").unwrap();
+
+        let decisions = explain_source_files(root, &ProjectConfig::default()).unwrap();
+        let listed: Vec<(&str, bool, &str)> = decisions
+            .iter()
+            .map(|d| (d.relative_path.as_str(), d.included, d.reason.as_str()))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                (".gitignore", true, "matches patterns.include `**/*`"),
+                (
+                    ".venv/",
+                    false,
+                    "ignored by `/.venv/` in the built-in defaults"
+                ),
+                ("dist/", false, "ignored by `dist/` in .gitignore"),
+                ("main.py", true, "matches patterns.include `**/*`"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resources_bypass_gitignore_and_excludes() {
+        let temp = tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("app/static")).unwrap();
+        fs::write(root.join("main.py"), b"").unwrap();
+        fs::write(root.join("app/static/app.js"), b"").unwrap();
+        fs::write(root.join("app/model.bin"), b"").unwrap();
+        fs::write(root.join(".gitignore"), b"app/static/\n*.bin\n").unwrap();
+
+        let config = ProjectConfig {
+            package: PackageConfig {
+                patterns: FilePatterns {
+                    include: vec!["**/*.py".to_string()],
+                    exclude: vec![],
+                },
+                resources: vec!["app/static".to_string(), "**/*.bin".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            collected_paths(root, &config),
+            vec!["app/model.bin", "app/static/app.js", "main.py"]
+        );
+    }
+
+    #[test]
+    fn test_resources_from_pyproject() {
+        let temp = tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("src/app/data")).unwrap();
+        fs::create_dir_all(root.join("assets")).unwrap();
+        fs::write(
+            root.join("pyproject.toml"),
+            r#"
+            [tool.setuptools.package-data]
+            app = ["data/*.json"]
+            "*" = ["*.typed"]
+
+            [tool.hatch.build.targets.wheel.force-include]
+            "assets" = "app/assets"
+            "../shared" = "app/shared"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            pyproject_resources(root),
+            vec!["**/*.typed", "src/app/data/*.json", "assets"]
+        );
+    }
+
+    #[test]
+    fn test_collect_source_files_rejects_invalid_glob() {
+        let temp = tempdir().unwrap();
+        fs::write(temp.path().join("main.py"), b"print('hi')").unwrap();
+
+        let config = ProjectConfig {
+            package: PackageConfig {
+                entrypoint: "main.py".into(),
+                patterns: FilePatterns {
+                    include: vec!["src/[abc".to_string()],
+                    exclude: vec![],
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let err = collect_source_files_with_config(temp.path(), &config).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("src/[abc"));
+    }
 }
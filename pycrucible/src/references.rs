@@ -0,0 +1,413 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use shared::debug_println;
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Name(String),
+    Str(String),
+    // f-strings are never a literal path
+    Formatted,
+    Punct(char),
+}
+
+/// What a referenced path is relative to.
+#[derive(Debug, PartialEq)]
+enum Base {
+    // The referencing file's directory, or the project root
+    File,
+    // `files("pkg")`, `read_text("pkg", ...)` and friends
+    Package(String),
+}
+
+/// A data file a Python source refers to through a string literal, e.g.
+/// `files("app") / "templates"` or `open("config.json")`.
+#[derive(Debug, PartialEq)]
+struct Reference {
+    line: usize,
+    path: String,
+    base: Base,
+}
+
+// `importlib.resources` functions from before `files()` and `pkgutil.get_data`, all of them take
+// the package followed by the resource name
+const PACKAGE_RESOURCE_CALLS: [&str; 7] = [
+    "read_text",
+    "read_binary",
+    "open_text",
+    "open_binary",
+    "is_resource",
+    "path",
+    "get_data",
+];
+
+// Reads the string literal whose opening quote is at `start`, returning its content with escapes
+// kept as written and the index past its closing quote
+fn read_string(chars: &[char], start: usize) -> (String, usize) {
+    let quote = chars[start];
+    let triple = chars.get(start + 1) == Some(&quote) && chars.get(start + 2) == Some(&quote);
+    let delimiter = if triple { 3 } else { 1 };
+    let mut value = String::new();
+    let mut i = start + delimiter;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\\' {
+            value.extend(chars.get(i..i + 2).unwrap_or(&chars[i..]));
+            i += 2;
+            continue;
+        }
+        if c == quote
+            && (!triple || (chars.get(i + 1) == Some(&quote) && chars.get(i + 2) == Some(&quote)))
+        {
+            return (value, i + delimiter);
+        }
+        if c == '\n' && !triple {
+            break;
+        }
+        value.push(c);
+        i += 1;
+    }
+    (value, i)
+}
+
+// Just enough of Python's lexer to find names, string literals and punctuation with their line
+fn tokenize(source: &str) -> Vec<(Token, usize)> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c == '#' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c.is_alphanumeric() || c == '_' || c == '\'' || c == '"' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let prefix: String = chars[start..i].iter().collect();
+            let is_string = i < chars.len()
+                && matches!(chars[i], '\'' | '"')
+                && prefix.len() <= 2
+                && prefix.chars().all(|p| "rRbBuUfF".contains(p));
+            if is_string {
+                let (value, end) = read_string(&chars, i);
+                let token = if prefix.contains(['f', 'F']) {
+                    Token::Formatted
+                } else {
+                    Token::Str(value)
+                };
+                tokens.push((token, line));
+                line += chars[i..end].iter().filter(|c| **c == '\n').count();
+                i = end;
+            } else {
+                tokens.push((Token::Name(prefix), line));
+            }
+        } else {
+            tokens.push((Token::Punct(c), line));
+            i += 1;
+        }
+    }
+    tokens
+}
+
+// String literals passed directly as arguments of the call opened at `open`, with their position
+fn string_args(tokens: &[(Token, usize)], open: usize) -> Vec<(usize, String)> {
+    let mut args = Vec::new();
+    let mut depth = 0;
+    let mut position = 0;
+    for (i, (token, _)) in tokens.iter().enumerate().skip(open) {
+        match token {
+            Token::Punct('(' | '[' | '{') => depth += 1,
+            Token::Punct(')' | ']' | '}') => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            Token::Punct(',') if depth == 1 => position += 1,
+            Token::Str(value)
+                if depth == 1
+                    && matches!(tokens[i - 1].0, Token::Punct('(' | ','))
+                    && matches!(tokens.get(i + 1), Some((Token::Punct(',' | ')'), _))) =>
+            {
+                args.push((position, value.clone()))
+            }
+            _ => {}
+        }
+    }
+    args
+}
+
+// A package given as `"pkg.sub"` or as a module object `pkg.sub` at `start`, with the index past
+// it. `__name__` and `__package__` are the referencing file's own package.
+fn package_arg(tokens: &[(Token, usize)], start: usize) -> Option<(Base, usize)> {
+    match &tokens.get(start)?.0 {
+        Token::Str(package) => Some((Base::Package(package.clone()), start + 1)),
+        Token::Name(first) => {
+            let mut parts = vec![first.clone()];
+            let mut i = start + 1;
+            while let (Some((Token::Punct('.'), _)), Some((Token::Name(part), _))) =
+                (tokens.get(i), tokens.get(i + 1))
+            {
+                parts.push(part.clone());
+                i += 2;
+            }
+            let base = if first.starts_with("__") {
+                Base::File
+            } else {
+                Base::Package(parts.join("."))
+            };
+            Some((base, i))
+        }
+        _ => None,
+    }
+}
+
+// `files(...)` earlier on the same line decides what joins on that line are relative to
+fn base_on_line(tokens: &[(Token, usize)], index: usize) -> Base {
+    let line = tokens[index].1;
+    (0..index)
+        .rev()
+        .take_while(|&i| tokens[i].1 == line)
+        .find_map(|i| match (&tokens[i].0, tokens.get(i + 1)) {
+            (Token::Name(name), Some((Token::Punct('('), _))) if name == "files" => {
+                package_arg(tokens, i + 2).map(|(base, _)| base)
+            }
+            _ => None,
+        })
+        .unwrap_or(Base::File)
+}
+
+fn find_references(source: &str) -> Vec<Reference> {
+    let tokens = tokenize(source);
+    let mut references = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let (token, line) = &tokens[i];
+        let opens_call = matches!(tokens.get(i + 1), Some((Token::Punct('('), _)));
+        match token {
+            Token::Name(name) if opens_call && name == "open" => {
+                if let Some((0, path)) = string_args(&tokens, i + 1).into_iter().next() {
+                    references.push(Reference {
+                        line: *line,
+                        path,
+                        base: base_on_line(&tokens, i),
+                    });
+                }
+            }
+            // `files(...).joinpath("a", "b")` and `os.path.join(os.path.dirname(__file__), "a")`
+            Token::Name(name) if opens_call && (name == "joinpath" || name == "join") => {
+                let parts: Vec<String> = string_args(&tokens, i + 1)
+                    .into_iter()
+                    .map(|(_, part)| part)
+                    .collect();
+                if !parts.is_empty() {
+                    references.push(Reference {
+                        line: *line,
+                        path: parts.join("/"),
+                        base: base_on_line(&tokens, i),
+                    });
+                }
+            }
+            Token::Name(name) if opens_call && PACKAGE_RESOURCE_CALLS.contains(&name.as_str()) => {
+                if let Some((base, next)) = package_arg(&tokens, i + 2)
+                    && let (Some((Token::Punct(','), _)), Some((Token::Str(path), _))) =
+                        (tokens.get(next), tokens.get(next + 1))
+                {
+                    references.push(Reference {
+                        line: *line,
+                        path: path.clone(),
+                        base,
+                    });
+                }
+            }
+            // `files("app") / "templates" / "index.html"` and `Path(__file__).parent / "data"`
+            Token::Punct('/') if i > 0 && !matches!(tokens[i - 1].0, Token::Str(_)) => {
+                let mut parts = Vec::new();
+                let mut next = i;
+                while let (Some((Token::Punct('/'), _)), Some((Token::Str(part), _))) =
+                    (tokens.get(next), tokens.get(next + 1))
+                {
+                    parts.push(part.clone());
+                    next += 2;
+                }
+                if !parts.is_empty() {
+                    references.push(Reference {
+                        line: *line,
+                        path: parts.join("/"),
+                        base: base_on_line(&tokens, i),
+                    });
+                    i = next;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    references
+}
+
+// `base/path` relative to the project root, `None` if it leaves the project
+fn join_relative(base: &str, path: &str) -> Option<String> {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in base.split('/').chain(path.split('/')) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            part => parts.push(part),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// Warnings for paths the collected Python files refer to (`open()`, `importlib.resources`,
+/// `Path(__file__).parent / ...`) that exist in `source_dir` but are not part of the payload.
+/// `collected` holds the payload's paths relative to `source_dir`, separated by `/`.
+pub fn uncollected_references(source_dir: &Path, collected: &HashSet<String>) -> Vec<String> {
+    let mut python_files: Vec<&String> = collected.iter().filter(|f| f.ends_with(".py")).collect();
+    python_files.sort();
+
+    let mut warnings = Vec::new();
+    for file in python_files {
+        let Ok(source) = fs::read_to_string(source_dir.join(file)) else {
+            continue;
+        };
+        let file_dir = file.rsplit_once('/').map_or("", |(dir, _)| dir);
+        for reference in find_references(&source) {
+            if reference.path.contains("://") || Path::new(&reference.path).is_absolute() {
+                continue;
+            }
+            let candidates = match &reference.base {
+                Base::File => vec![
+                    join_relative(file_dir, &reference.path),
+                    join_relative("", &reference.path),
+                ],
+                Base::Package(package) => {
+                    let package_dir = package.replace('.', "/");
+                    vec![
+                        join_relative(&package_dir, &reference.path),
+                        join_relative(&format!("src/{}", package_dir), &reference.path),
+                    ]
+                }
+            };
+            let Some(relative) = candidates
+                .into_iter()
+                .flatten()
+                .find(|path| !path.is_empty() && source_dir.join(path).exists())
+            else {
+                continue;
+            };
+            let prefix = format!("{}/", relative);
+            if collected.contains(&relative) || collected.iter().any(|c| c.starts_with(&prefix)) {
+                continue;
+            }
+            debug_println!(
+                "[references.uncollected_references] - {}:{} refers to {:?}",
+                file,
+                reference.line,
+                relative
+            );
+            let warning = format!(
+                "{}:{} refers to `{}`, which is not in the payload, add it to `resources`",
+                file, reference.line, relative
+            );
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn paths(source: &str) -> Vec<(usize, String, Base)> {
+        find_references(source)
+            .into_iter()
+            .map(|r| (r.line, r.path, r.base))
+            .collect()
+    }
+
+    #[test]
+    fn test_find_references() {
+        let source = r#"
+import json, os
+from importlib.resources import files, read_text
+from pathlib import Path
+
+HERE = Path(__file__).parent / "data" / "defaults.json"  # "ignored.txt"
+TEMPLATES = files("app.web") / "templates"
+schema = files(__package__).joinpath("schema", "v1.json")
+legacy = read_text("app", "VERSION")
+with open("config.json", encoding="utf-8") as f:
+    pass
+other = os.path.join(os.path.dirname(__file__), "static")
+label = ", ".join(names)
+ratio = total / 2
+message = f"{name}.txt"
+log = open(f"{name}.log")
+"#;
+        assert_eq!(
+            paths(source),
+            vec![
+                (6, "data/defaults.json".to_string(), Base::File),
+                (
+                    7,
+                    "templates".to_string(),
+                    Base::Package("app.web".to_string())
+                ),
+                (8, "schema/v1.json".to_string(), Base::File),
+                (9, "VERSION".to_string(), Base::Package("app".to_string())),
+                (10, "config.json".to_string(), Base::File),
+                (12, "static".to_string(), Base::File),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_uncollected_references() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src/app/templates")).unwrap();
+        fs::write(root.join("src/app/templates/index.html"), b"").unwrap();
+        fs::write(root.join("src/app/VERSION"), b"1.0").unwrap();
+        fs::write(root.join("settings.toml"), b"").unwrap();
+        fs::write(
+            root.join("src/app/web.py"),
+            b"from importlib.resources import files\n\
+              INDEX = files('app') / 'templates' / 'index.html'\n\
+              VERSION = files('app').joinpath('VERSION').read_text()\n\
+              SETTINGS = open('settings.toml')\n\
+              MISSING = open('missing.json')\n",
+        )
+        .unwrap();
+
+        let collected: HashSet<String> = ["src/app/web.py", "src/app/VERSION"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            uncollected_references(root, &collected),
+            vec![
+                "src/app/web.py:2 refers to `src/app/templates/index.html`, which is not in the payload, add it to `resources`",
+                "src/app/web.py:4 refers to `settings.toml`, which is not in the payload, add it to `resources`",
+            ]
+        );
+    }
+}
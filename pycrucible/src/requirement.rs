@@ -0,0 +1,450 @@
+use std::cmp::Ordering;
+
+/// `name[extra,...]` followed by comma separated version specifiers, e.g. `httpie[socks]>=3.2,<4`.
+/// Markers and URLs are not supported.
+#[derive(Debug, PartialEq)]
+pub struct Requirement {
+    pub name: String,
+    pub extras: Vec<String>,
+    pub specifiers: Vec<Specifier>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    LessEqual,
+    GreaterEqual,
+    Less,
+    Greater,
+    Compatible,
+    Arbitrary,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Specifier {
+    pub operator: Operator,
+    pub version: String,
+}
+
+/// A release file of a distribution, named like `my_app-1.0-py3-none-any.whl` or `my_app-1.0.tar.gz`.
+#[derive(Debug, PartialEq)]
+pub struct DistributionFile {
+    pub name: String,
+    pub version: PyVersion,
+    // `None` for source distributions, `any` for pure Python wheels
+    pub platform: Option<String>,
+    // Python tag of wheels, e.g. `py3` or `py2.py3`
+    pub python: Option<String>,
+}
+
+/// A PEP 440 version, compared the way pip and uv compare them. Local versions are ignored.
+#[derive(Debug, Clone)]
+pub struct PyVersion {
+    raw: String,
+    epoch: u64,
+    release: Vec<u64>,
+    // (0 for `a`, 1 for `b`, 2 for `rc`, number)
+    pre: Option<(u8, u64)>,
+    post: Option<u64>,
+    dev: Option<u64>,
+}
+
+/// `My_App.Name` and `my-app-name` are the same project.
+pub fn normalize_name(name: &str) -> String {
+    let mut normalized = String::new();
+    for c in name.chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !normalized.ends_with('-') {
+                normalized.push('-');
+            }
+        } else {
+            normalized.push(c.to_ascii_lowercase());
+        }
+    }
+    normalized
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')
+}
+
+// Splits a leading run of digits off `rest`
+fn take_number(rest: &mut &str) -> Option<u64> {
+    let end = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let number = rest[..end].parse().ok()?;
+    *rest = &rest[end..];
+    Some(number)
+}
+
+// Consumes one of `labels` after an optional separator, followed by an optional number
+fn take_label(rest: &mut &str, labels: &[&str]) -> Option<(usize, u64)> {
+    let trimmed = rest.trim_start_matches(['.', '-', '_']);
+    let (index, label) = labels
+        .iter()
+        .enumerate()
+        .filter(|(_, label)| trimmed.starts_with(**label))
+        .max_by_key(|(_, label)| label.len())?;
+    let mut after = trimmed[label.len()..].trim_start_matches(['.', '-', '_']);
+    let number = take_number(&mut after).unwrap_or(0);
+    *rest = after;
+    Some((index, number))
+}
+
+impl PyVersion {
+    pub fn parse(raw: &str) -> Option<Self> {
+        let lowered = raw.trim().to_ascii_lowercase();
+        let mut rest = lowered.strip_prefix('v').unwrap_or(&lowered);
+        if let Some((local_free, _)) = rest.split_once('+') {
+            rest = local_free;
+        }
+
+        let epoch = match rest.split_once('!') {
+            Some((epoch, version)) => {
+                rest = version;
+                epoch.parse().ok()?
+            }
+            None => 0,
+        };
+        let mut release = vec![take_number(&mut rest)?];
+        while let Some(next) = rest.strip_prefix('.')
+            && next.starts_with(|c: char| c.is_ascii_digit())
+        {
+            rest = next;
+            release.push(take_number(&mut rest)?);
+        }
+
+        const PRE: [&str; 7] = ["a", "alpha", "b", "beta", "rc", "c", "pre"];
+        let pre = take_label(&mut rest, &PRE).map(|(index, number)| {
+            let rank = match PRE[index] {
+                "a" | "alpha" => 0,
+                "b" | "beta" => 1,
+                _ => 2,
+            };
+            (rank, number)
+        });
+        let post = match take_label(&mut rest, &["post", "rev", "r"]) {
+            Some((_, number)) => Some(number),
+            // `1.0-1` is an implicit post release
+            None => match rest.strip_prefix('-') {
+                Some(mut after) if after.starts_with(|c: char| c.is_ascii_digit()) => {
+                    let number = take_number(&mut after);
+                    rest = after;
+                    number
+                }
+                _ => None,
+            },
+        };
+        let dev = take_label(&mut rest, &["dev"]).map(|(_, number)| number);
+        if !rest.is_empty() {
+            return None;
+        }
+
+        Some(PyVersion {
+            raw: raw.trim().to_string(),
+            epoch,
+            release,
+            pre,
+            post,
+            dev,
+        })
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        self.pre.is_some() || self.dev.is_some()
+    }
+
+    // Release segments without trailing zeros, `1.0` equals `1.0.0`
+    fn trimmed_release(&self) -> &[u64] {
+        let end = self
+            .release
+            .iter()
+            .rposition(|&n| n != 0)
+            .map_or(0, |i| i + 1);
+        &self.release[..end]
+    }
+
+    fn sort_key(&self) -> (u64, &[u64], (i64, i64), i64, i64) {
+        let pre = match (self.pre, self.post, self.dev) {
+            (Some((rank, number)), _, _) => (rank as i64, number as i64),
+            // `1.0.dev1` comes before `1.0a1`
+            (None, None, Some(_)) => (-1, 0),
+            _ => (3, 0),
+        };
+        (
+            self.epoch,
+            self.trimmed_release(),
+            pre,
+            self.post.map_or(-1, |n| n as i64),
+            self.dev.map_or(i64::MAX, |n| n as i64),
+        )
+    }
+
+    // `==1.2.*` matches every version whose release starts with 1.2
+    fn has_release_prefix(&self, prefix: &[u64]) -> bool {
+        let padded = self.release.iter().copied().chain(std::iter::repeat(0));
+        prefix.iter().zip(padded).all(|(a, b)| *a == b)
+    }
+}
+
+impl std::fmt::Display for PyVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+impl PartialEq for PyVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PyVersion {}
+
+impl PartialOrd for PyVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PyVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+impl Specifier {
+    fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim();
+        let operators = [
+            ("===", Operator::Arbitrary),
+            ("==", Operator::Equal),
+            ("!=", Operator::NotEqual),
+            ("<=", Operator::LessEqual),
+            (">=", Operator::GreaterEqual),
+            ("~=", Operator::Compatible),
+            ("<", Operator::Less),
+            (">", Operator::Greater),
+        ];
+        let (operator, version) = operators
+            .iter()
+            .find_map(|(prefix, operator)| Some((*operator, raw.strip_prefix(prefix)?)))?;
+        let version = version.trim().to_string();
+        let wildcard = version.strip_suffix(".*");
+        let valid = match (operator, wildcard) {
+            (Operator::Arbitrary, _) => !version.is_empty(),
+            (Operator::Equal | Operator::NotEqual, Some(prefix)) => {
+                PyVersion::parse(prefix).is_some()
+            }
+            (Operator::Compatible, None) => {
+                PyVersion::parse(&version).is_some_and(|v| v.release.len() > 1)
+            }
+            (_, None) => PyVersion::parse(&version).is_some(),
+            (_, Some(_)) => false,
+        };
+        valid.then_some(Specifier { operator, version })
+    }
+
+    pub fn matches(&self, candidate: &PyVersion) -> bool {
+        if self.operator == Operator::Arbitrary {
+            return candidate.raw == self.version;
+        }
+        if let Some(prefix) = self.version.strip_suffix(".*") {
+            let prefix = PyVersion::parse(prefix).expect("validated when parsed");
+            let matched =
+                candidate.epoch == prefix.epoch && candidate.has_release_prefix(&prefix.release);
+            return matched == (self.operator == Operator::Equal);
+        }
+
+        let version = PyVersion::parse(&self.version).expect("validated when parsed");
+        match self.operator {
+            Operator::Equal => *candidate == version,
+            Operator::NotEqual => *candidate != version,
+            Operator::LessEqual => *candidate <= version,
+            Operator::GreaterEqual => *candidate >= version,
+            Operator::Less => *candidate < version,
+            Operator::Greater => *candidate > version,
+            // `~=1.4.2` means `>=1.4.2, ==1.4.*`
+            Operator::Compatible => {
+                let prefix = &version.release[..version.release.len() - 1];
+                *candidate >= version && candidate.has_release_prefix(prefix)
+            }
+            Operator::Arbitrary => unreachable!(),
+        }
+    }
+
+    fn allows_prereleases(&self) -> bool {
+        let version = self.version.strip_suffix(".*").unwrap_or(&self.version);
+        PyVersion::parse(version).is_some_and(|v| v.is_prerelease())
+    }
+}
+
+impl Requirement {
+    /// `None` if `raw` is not a plain requirement (a path, URL or environment marker for example).
+    pub fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim();
+        let name_end = raw.find(|c: char| !is_name_char(c)).unwrap_or(raw.len());
+        let name = &raw[..name_end];
+        if name.is_empty()
+            || !name.starts_with(|c: char| c.is_ascii_alphanumeric())
+            || !name.ends_with(|c: char| c.is_ascii_alphanumeric())
+        {
+            return None;
+        }
+
+        let mut rest = raw[name_end..].trim_start();
+        let mut extras = Vec::new();
+        if let Some(after) = rest.strip_prefix('[') {
+            let (list, after) = after.split_once(']')?;
+            for extra in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                if !extra.chars().all(is_name_char) {
+                    return None;
+                }
+                extras.push(extra.to_string());
+            }
+            rest = after.trim_start();
+        }
+
+        let specifiers = if rest.is_empty() {
+            Vec::new()
+        } else {
+            rest.split(',')
+                .map(Specifier::parse)
+                .collect::<Option<Vec<_>>>()?
+        };
+        Some(Requirement {
+            name: name.to_string(),
+            extras,
+            specifiers,
+        })
+    }
+
+    /// Pre-releases only match when a specifier names one, like pip and uv do by default.
+    pub fn matches(&self, version: &PyVersion) -> bool {
+        if version.is_prerelease() && !self.specifiers.iter().any(Specifier::allows_prereleases) {
+            return false;
+        }
+        self.specifiers.iter().all(|s| s.matches(version))
+    }
+}
+
+impl DistributionFile {
+    /// Parses wheel and sdist (`.tar.gz` or `.zip`) file names, `None` for anything else.
+    pub fn parse(file_name: &str) -> Option<Self> {
+        if let Some(stem) = file_name.strip_suffix(".whl") {
+            let parts: Vec<&str> = stem.split('-').collect();
+            // name-version[-build]-python-abi-platform
+            if !(5..=6).contains(&parts.len()) {
+                return None;
+            }
+            return Some(DistributionFile {
+                name: normalize_name(parts[0]),
+                version: PyVersion::parse(parts[1])?,
+                platform: Some(parts[parts.len() - 1].to_string()),
+                python: Some(parts[parts.len() - 3].to_string()),
+            });
+        }
+        let stem = file_name
+            .strip_suffix(".tar.gz")
+            .or_else(|| file_name.strip_suffix(".zip"))?;
+        let (name, version) = stem.rsplit_once('-')?;
+        Some(DistributionFile {
+            name: normalize_name(name),
+            version: PyVersion::parse(version)?,
+            platform: None,
+            python: None,
+        })
+    }
+
+    /// A wheel for any platform that runs on Python 3, `py2-none-any` wheels do not.
+    pub fn is_pure_wheel(&self) -> bool {
+        self.platform.as_deref() == Some("any")
+            && self
+                .python
+                .as_deref()
+                .is_some_and(|python| python.split('.').any(|tag| tag.starts_with("py3")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(raw: &str) -> PyVersion {
+        PyVersion::parse(raw).unwrap()
+    }
+
+    #[test]
+    fn test_version_ordering() {
+        let ordered = [
+            "1.0.dev1",
+            "1.0a1",
+            "1.0a2.dev1",
+            "1.0b1",
+            "1.0rc1",
+            "1.0",
+            "1.0.post1",
+            "1.1",
+            "2!0.1",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(version(pair[0]) < version(pair[1]), "{:?}", pair);
+        }
+        assert_eq!(version("1.0"), version("1.0.0"));
+        assert_eq!(version("v1.0-1"), version("1.0.post1"));
+        assert!(PyVersion::parse("1.0-beta-x").is_none());
+    }
+
+    #[test]
+    fn test_requirement_matching() {
+        let requirement = Requirement::parse("HTTPie[socks, yaml] >=3.2,<4,!=3.2.1").unwrap();
+        assert_eq!(requirement.name, "HTTPie");
+        assert_eq!(requirement.extras, ["socks", "yaml"]);
+        assert!(requirement.matches(&version("3.2.2")));
+        assert!(!requirement.matches(&version("3.2.1")));
+        assert!(!requirement.matches(&version("4.0")));
+        assert!(!requirement.matches(&version("3.3b1")));
+
+        let compatible = Requirement::parse("tool~=1.4.2").unwrap();
+        assert!(compatible.matches(&version("1.4.9")));
+        assert!(!compatible.matches(&version("1.5.0")));
+        let wildcard = Requirement::parse("tool==2.*").unwrap();
+        assert!(wildcard.matches(&version("2.9")));
+        assert!(!wildcard.matches(&version("3.0")));
+        assert!(
+            Requirement::parse("tool==2.0b1")
+                .unwrap()
+                .matches(&version("2.0b1"))
+        );
+
+        for invalid in ["./app", "app.whl ; python_version<'3'", "app>=", "-app"] {
+            assert!(Requirement::parse(invalid).is_none(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_distribution_file_names() {
+        let wheel = DistributionFile::parse("My_App-1.0-py3-none-any.whl").unwrap();
+        assert_eq!(wheel.name, "my-app");
+        assert!(wheel.is_pure_wheel());
+        assert!(
+            DistributionFile::parse("six-1.16.0-py2.py3-none-any.whl")
+                .unwrap()
+                .is_pure_wheel()
+        );
+        assert!(
+            !DistributionFile::parse("futures-3.4.0-py2-none-any.whl")
+                .unwrap()
+                .is_pure_wheel()
+        );
+        let native =
+            DistributionFile::parse("my_app-1.0-1-cp312-cp312-manylinux_2_17_x86_64.whl").unwrap();
+        assert!(!native.is_pure_wheel());
+        let sdist = DistributionFile::parse("my_app-1.0.tar.gz").unwrap();
+        assert_eq!(sdist.version, version("1.0"));
+        assert_eq!(sdist.platform, None);
+        assert!(DistributionFile::parse("my_app-1.0.exe").is_none());
+    }
+}
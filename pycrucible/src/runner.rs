@@ -30,13 +30,13 @@ mod tests {
 
     // Override the function just for test
     fn extract_runner_test(output_path: &Path, content: &[u8]) -> io::Result<()> {
-        std::fs::write(&output_path, content)?;
+        std::fs::write(output_path, content)?;
         #[cfg(unix)]
         {
             use std::os::unix::fs::PermissionsExt;
-            let mut perms = std::fs::metadata(&output_path)?.permissions();
+            let mut perms = std::fs::metadata(output_path)?.permissions();
             perms.set_mode(0o755);
-            std::fs::set_permissions(&output_path, perms)?;
+            std::fs::set_permissions(output_path, perms)?;
         }
         Ok(())
     }
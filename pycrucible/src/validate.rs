@@ -0,0 +1,404 @@
+use std::collections::HashSet;
+use std::io;
+use std::path::{Component, Path};
+
+use crate::config::{EntrypointKind, EntrypointSpec, HookKind, ProjectConfig};
+use crate::debug_println;
+use crate::project::CollectedSources;
+use crate::references;
+
+// `entrypoint = "./src/main.py"` and `src\main.py` should match the collected `src/main.py`
+fn normalize(path: &str) -> String {
+    Path::new(&path.replace('\\', "/"))
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn is_valid_command_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+// Console scripts declared in `[project.scripts]`, `None` if the project does not declare any
+fn declared_scripts(source_dir: &Path) -> Option<Vec<String>> {
+    let raw = std::fs::read_to_string(source_dir.join("pyproject.toml")).ok()?;
+    let doc: toml::Value = toml::from_str(&raw).ok()?;
+    let scripts = doc.get("project")?.get("scripts")?.as_table()?;
+    Some(scripts.keys().cloned().collect())
+}
+
+fn check_included(
+    errors: &mut Vec<String>,
+    what: &str,
+    path: &str,
+    source_dir: &Path,
+    collected: &HashSet<String>,
+) {
+    if collected.contains(&normalize(path)) {
+        return;
+    }
+    if source_dir.join(path).exists() {
+        errors.push(format!(
+            "{} `{}` exists but is not included in the payload, check `patterns.include` and `patterns.exclude`",
+            what, path
+        ));
+    } else {
+        errors.push(format!(
+            "{} `{}` not found in {}",
+            what,
+            path,
+            source_dir.display()
+        ));
+    }
+}
+
+// `package.module` is importable from the payload if one of its files was collected, at the
+// root or in a `src` layout
+fn module_collected(module: &str, collected: &HashSet<String>) -> bool {
+    let path = module.replace('.', "/");
+    ["", "src/"].iter().any(|prefix| {
+        [".py", "/__init__.py"]
+            .iter()
+            .any(|suffix| collected.contains(&format!("{}{}{}", prefix, path, suffix)))
+    })
+}
+
+fn check_entrypoint(
+    errors: &mut Vec<String>,
+    what: &str,
+    entrypoint: &EntrypointSpec,
+    source_dir: &Path,
+    collected: &HashSet<String>,
+) {
+    match entrypoint.kind() {
+        Ok(EntrypointKind::Script(path) | EntrypointKind::Wheel(path)) => {
+            check_included(errors, what, path, source_dir, collected)
+        }
+        Ok(EntrypointKind::Module(module) | EntrypointKind::Callable { module, .. }) => {
+            // It may just as well come from an installed dependency
+            if !module_collected(module, collected) {
+                eprintln!(
+                    "Warning: {} module `{}` is not in the payload, it has to come from a dependency",
+                    what, module
+                );
+            }
+        }
+        Ok(EntrypointKind::ConsoleScript(name)) if !is_valid_command_name(name) => {
+            errors.push(format!(
+                "{} `{}` is neither a .py file nor a valid console script or module name",
+                what, name
+            ));
+        }
+        Ok(EntrypointKind::ConsoleScript(name)) => {
+            if let Some(scripts) = declared_scripts(source_dir)
+                && !scripts.iter().any(|script| script == name)
+            {
+                eprintln!(
+                    "Warning: {} `{}` is not declared in [project.scripts] ({})",
+                    what,
+                    name,
+                    scripts.join(", ")
+                );
+            }
+        }
+        Err(e) => errors.push(format!("{}: {}", what, e)),
+    }
+}
+
+/// Checks that the collected payload can actually be run, before the output binary is written.
+/// Every problem is reported at once rather than one per build.
+pub fn validate_build(
+    source_dir: &Path,
+    config: &ProjectConfig,
+    sources: &CollectedSources,
+    manifest: Option<&Path>,
+) -> io::Result<()> {
+    let files = match sources {
+        // The entrypoint is read from the wheel metadata
+        CollectedSources::Wheel { .. } => return Ok(()),
+        CollectedSources::Files(files) => files,
+    };
+
+    let mut errors = Vec::new();
+    if manifest.is_none() {
+        errors.push(format!(
+            "no manifest found in {} (pyproject.toml, requirements.txt, pylock.toml, setup.py or setup.cfg)",
+            source_dir.display()
+        ));
+    }
+
+    if config.source.is_some() {
+        // Entrypoint and hooks come from the repository checkout at runtime
+        debug_println!(
+            "[validate.validate_build] - [source] is configured, skipping entrypoint and hook checks"
+        );
+    } else {
+        let source_dir = source_dir.canonicalize()?;
+        let collected: HashSet<String> = files
+            .iter()
+            .filter_map(|f| f.absolute_path.strip_prefix(&source_dir).ok())
+            .map(|p| normalize(&p.to_string_lossy()))
+            .collect();
+
+        // Data files left out of the payload only fail once the app opens them
+        for warning in references::uncollected_references(&source_dir, &collected) {
+            eprintln!("Warning: {}", warning);
+        }
+
+        let package = &config.package;
+        let default_is_named = package
+            .entrypoint
+            .as_name()
+            .is_some_and(|name| package.entrypoints.contains_key(name));
+        if package.entrypoint.is_empty() && package.entrypoints.is_empty() {
+            errors.push("entrypoint is empty".to_string());
+        } else if !package.entrypoint.is_empty() && !default_is_named {
+            check_entrypoint(
+                &mut errors,
+                "entrypoint",
+                &package.entrypoint,
+                &source_dir,
+                &collected,
+            );
+        }
+        for (name, entrypoint) in &package.entrypoints {
+            // The name is matched against the file name the binary is invoked as
+            if !is_valid_command_name(name) {
+                errors.push(format!("`{}` is not a valid command name", name));
+            }
+            check_entrypoint(
+                &mut errors,
+                &format!("entrypoints.{}", name),
+                entrypoint,
+                &source_dir,
+                &collected,
+            );
+        }
+
+        // Platform hooks are checked too, the binary may be run on any of them
+        let platform_hooks = config
+            .platform
+            .sections()
+            .filter_map(|(platform, section)| {
+                Some((format!("platform.{}.", platform), section.hooks.as_ref()?))
+            });
+        for (prefix, hooks) in config
+            .hooks
+            .iter()
+            .map(|hooks| (String::new(), hooks))
+            .chain(platform_hooks)
+        {
+            for (point, hook) in hooks.all() {
+                match hook.kind() {
+                    Ok(HookKind::Script(script)) if !script.is_empty() => check_included(
+                        &mut errors,
+                        &format!("{}{} hook", prefix, point),
+                        script,
+                        &source_dir,
+                        &collected,
+                    ),
+                    // Modules and commands are resolved at runtime
+                    Ok(_) => {}
+                    Err(e) => errors.push(format!("{}{} hook: {}", prefix, point, e)),
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Build validation failed:\n  - {}", errors.join("\n  - ")),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{
+        EntrypointEntry, HookEntry, HookList, HookSpec, Hooks, PackageConfig, PlatformConfig,
+        PlatformSections,
+    };
+    use crate::project::SourceFile;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn collected(root: &Path, files: &[&str]) -> CollectedSources {
+        CollectedSources::Files(
+            files
+                .iter()
+                .map(|f| SourceFile {
+                    absolute_path: root.canonicalize().unwrap().join(f),
+                })
+                .collect(),
+        )
+    }
+
+    fn config_with_entrypoint(entrypoint: &str) -> ProjectConfig {
+        ProjectConfig {
+            package: PackageConfig {
+                entrypoint: entrypoint.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate_build_accepts_collected_entrypoint() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/main.py"), b"").unwrap();
+        let manifest = dir.path().join("pyproject.toml");
+
+        let result = validate_build(
+            dir.path(),
+            &config_with_entrypoint("./src/main.py"),
+            &collected(dir.path(), &["src/main.py"]),
+            Some(&manifest),
+        );
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_validate_build_reports_excluded_entrypoint_and_missing_manifest() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("main.py"), b"").unwrap();
+
+        let err = validate_build(
+            dir.path(),
+            &config_with_entrypoint("main.py"),
+            &collected(dir.path(), &[]),
+            None,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("no manifest found"));
+        assert!(err.contains("entrypoint `main.py` exists but is not included"));
+    }
+
+    #[test]
+    fn test_validate_build_reports_missing_hook() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("main.py"), b"").unwrap();
+        let manifest = dir.path().join("requirements.txt");
+        let config = ProjectConfig {
+            hooks: Some(Hooks {
+                pre_run: Some(HookList::One(HookSpec::Script(
+                    "scripts/setup.py".to_string(),
+                ))),
+                on_error: Some(HookList::One(HookSpec::Entry(HookEntry::default()))),
+                ..Default::default()
+            }),
+            ..config_with_entrypoint("main.py")
+        };
+
+        let err = validate_build(
+            dir.path(),
+            &config,
+            &collected(dir.path(), &["main.py"]),
+            Some(&manifest),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("pre_run hook `scripts/setup.py` not found"));
+        assert!(err.contains("on_error hook: a hook needs exactly one of"));
+
+        let config = ProjectConfig {
+            platform: PlatformSections {
+                windows: Some(PlatformConfig {
+                    hooks: Some(Hooks {
+                        post_run: Some(HookList::Many(vec![HookSpec::Script(
+                            "scripts/cleanup.ps1".to_string(),
+                        )])),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..config_with_entrypoint("main.py")
+        };
+        let err = validate_build(
+            dir.path(),
+            &config,
+            &collected(dir.path(), &["main.py"]),
+            Some(&manifest),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("platform.windows.post_run hook `scripts/cleanup.ps1` not found"));
+    }
+
+    #[test]
+    fn test_validate_build_checks_named_entrypoints() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("serve.py"), b"").unwrap();
+        let manifest = dir.path().join("requirements.txt");
+        let sources = collected(dir.path(), &["serve.py"]);
+        let mut config = config_with_entrypoint("");
+        config.package.entrypoints = [
+            ("serve".to_string(), "serve.py".into()),
+            ("bad name".to_string(), "migrate.py".into()),
+            (
+                "shell".to_string(),
+                EntrypointSpec::Entry(EntrypointEntry {
+                    callable: Some("myapp.shell".to_string()),
+                    ..Default::default()
+                }),
+            ),
+        ]
+        .into();
+
+        let err = validate_build(dir.path(), &config, &sources, Some(&manifest))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("`bad name` is not a valid command name"));
+        assert!(err.contains("entrypoints.bad name `migrate.py` not found"));
+        assert!(err.contains(
+            "entrypoints.shell: callable `myapp.shell` must look like `package.module:function`"
+        ));
+        assert!(!err.contains("entrypoints.serve"));
+
+        config.package.entrypoints.remove("bad name");
+        config.package.entrypoints.remove("shell");
+        config.package.entrypoint = "serve".into();
+        assert!(validate_build(dir.path(), &config, &sources, Some(&manifest)).is_ok());
+    }
+
+    #[test]
+    fn test_validate_build_checks_console_script_names() {
+        let dir = tempdir().unwrap();
+        let manifest = dir.path().join("pyproject.toml");
+        let sources = collected(dir.path(), &[]);
+
+        assert!(
+            validate_build(
+                dir.path(),
+                &config_with_entrypoint("my-app"),
+                &sources,
+                Some(&manifest)
+            )
+            .is_ok()
+        );
+        assert!(
+            validate_build(
+                dir.path(),
+                &config_with_entrypoint("src/main"),
+                &sources,
+                Some(&manifest)
+            )
+            .is_err()
+        );
+    }
+}
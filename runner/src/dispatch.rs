@@ -0,0 +1,175 @@
+use std::path::Path;
+
+use shared::config::{EntrypointSpec, PackageConfig};
+
+/// What the binary was asked to do.
+#[derive(Debug, PartialEq)]
+pub enum Selection {
+    Run {
+        entrypoint: EntrypointSpec,
+        args: Vec<String>,
+    },
+    // Print the command list, with an error if no command could be picked
+    Help {
+        error: Option<String>,
+    },
+}
+
+/// The file name the binary was started as, without `.exe`. Links to the binary keep their own name.
+pub fn invoked_name(argv0: &str) -> Option<String> {
+    Path::new(argv0)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+}
+
+/// Picks the entrypoint busybox-style: a named entrypoint matching the name the binary was
+/// invoked as, then one matching the first argument, then the default entrypoint.
+pub fn select(package: &PackageConfig, invoked_as: Option<&str>, args: Vec<String>) -> Selection {
+    let entrypoints = &package.entrypoints;
+    let default = package.default_entrypoint().cloned();
+    if entrypoints.is_empty() {
+        return Selection::Run {
+            entrypoint: default.unwrap_or_else(|| package.entrypoint.clone()),
+            args,
+        };
+    }
+
+    if let Some(entrypoint) = invoked_as.and_then(|name| entrypoints.get(name)) {
+        return Selection::Run {
+            entrypoint: entrypoint.clone(),
+            args,
+        };
+    }
+    match args.first().map(String::as_str) {
+        Some(command) if entrypoints.contains_key(command) => Selection::Run {
+            entrypoint: entrypoints[command].clone(),
+            args: args[1..].to_vec(),
+        },
+        Some("--help" | "-h") => Selection::Help { error: None },
+        first => match default {
+            Some(entrypoint) => Selection::Run { entrypoint, args },
+            None => Selection::Help {
+                error: Some(match first {
+                    Some(command) => format!("unknown command `{}`", command),
+                    None => "no command given".to_string(),
+                }),
+            },
+        },
+    }
+}
+
+/// The generated `--help` listing the named entrypoints.
+pub fn help_text(binary: &str, package: &PackageConfig) -> String {
+    let width = package
+        .entrypoints
+        .keys()
+        .map(|name| name.len())
+        .max()
+        .unwrap_or(0);
+    let mut text = format!("Usage: {} <command> [args...]\n\nCommands:\n", binary);
+    for (name, entrypoint) in &package.entrypoints {
+        let default = if package.entrypoint == name.as_str() {
+            " (default)"
+        } else {
+            ""
+        };
+        text.push_str(&format!(
+            "  {:width$}  {}{}\n",
+            name,
+            entrypoint,
+            default,
+            width = width
+        ));
+    }
+    text.push_str(&format!(
+        "\nCommands can also be run through a link to {} named after them.\n",
+        binary
+    ));
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::config::EntrypointEntry;
+
+    fn package(entrypoint: &str) -> PackageConfig {
+        PackageConfig {
+            entrypoint: entrypoint.into(),
+            entrypoints: [
+                ("serve".to_string(), "src/serve.py".into()),
+                ("migrate".to_string(), "src/migrate.py".into()),
+                (
+                    "shell".to_string(),
+                    EntrypointSpec::Entry(EntrypointEntry {
+                        module: Some("myapp.shell".to_string()),
+                        ..Default::default()
+                    }),
+                ),
+            ]
+            .into(),
+            ..Default::default()
+        }
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn run(entrypoint: &str, arguments: &[&str]) -> Selection {
+        Selection::Run {
+            entrypoint: entrypoint.into(),
+            args: args(arguments),
+        }
+    }
+
+    #[test]
+    fn test_select_entrypoint() {
+        let suite = package("");
+        assert_eq!(
+            select(&suite, Some("migrate"), args(&["--dry-run"])),
+            run("src/migrate.py", &["--dry-run"])
+        );
+        assert_eq!(
+            select(&suite, Some("suite"), args(&["serve", "--port", "80"])),
+            run("src/serve.py", &["--port", "80"])
+        );
+        assert_eq!(
+            select(&suite, Some("suite"), args(&["--help"])),
+            Selection::Help { error: None }
+        );
+        assert_eq!(
+            select(&suite, Some("suite"), args(&["deploy"])),
+            Selection::Help {
+                error: Some("unknown command `deploy`".to_string())
+            }
+        );
+
+        let with_default = package("serve");
+        assert_eq!(
+            select(&with_default, Some("suite"), args(&["--port", "80"])),
+            run("src/serve.py", &["--port", "80"])
+        );
+
+        let single = PackageConfig::default();
+        assert_eq!(
+            select(&single, Some("app"), args(&["--help"])),
+            run("main.py", &["--help"])
+        );
+    }
+
+    #[test]
+    fn test_invoked_name_and_help() {
+        assert_eq!(
+            invoked_name("/usr/local/bin/migrate").as_deref(),
+            Some("migrate")
+        );
+        assert_eq!(invoked_name("serve.exe").as_deref(), Some("serve"));
+
+        let help = help_text("suite", &package("serve"));
+        assert!(help.contains("Usage: suite <command>"));
+        assert!(help.contains("  migrate  src/migrate.py\n"));
+        assert!(help.contains("  serve    src/serve.py (default)\n"));
+        assert!(help.contains("  shell    -m myapp.shell\n"));
+    }
+}
@@ -0,0 +1,171 @@
+use crate::environment::interpolate;
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.'))
+}
+
+// Content of a double quoted value starting after the opening `"`, it may continue on the
+// following lines. Returns the unescaped value and what follows the closing quote.
+fn read_double_quoted<'a>(
+    first: &'a str,
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+) -> Option<(String, &'a str)> {
+    let mut value = String::new();
+    let mut current = first;
+    loop {
+        let mut chars = current.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Some((value, &current[i + 1..])),
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, 't')) => value.push('\t'),
+                    Some((_, 'r')) => value.push('\r'),
+                    Some((_, other)) => value.push(other),
+                    None => value.push('\\'),
+                },
+                _ => value.push(c),
+            }
+        }
+        let (_, next) = lines.next()?;
+        value.push('\n');
+        current = next;
+    }
+}
+
+// Only whitespace or a comment may follow a quoted value
+fn check_trailing(rest: &str, line: usize) -> Result<(), String> {
+    let rest = rest.trim();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(())
+    } else {
+        Err(format!(
+            "line {}: unexpected `{}` after quoted value",
+            line, rest
+        ))
+    }
+}
+
+/// Parses `.env` content into `(name, value)` pairs in file order.
+/// Lines are `NAME=value`, optionally prefixed with `export`, `#` starts a comment. Single quoted
+/// values are taken literally, double quoted values may span lines and support `\n`, `\t`, `\"`
+/// and `\\`. Unquoted and double quoted values are interpolated like `[env]`, with names set
+/// earlier in the same file taking precedence over `lookup`.
+pub fn parse(
+    content: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<Vec<(String, String)>, String> {
+    let mut entries: Vec<(String, String)> = Vec::new();
+    let mut lines = content.lines().enumerate().map(|(i, line)| (i + 1, line));
+    while let Some((number, line)) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line
+            .strip_prefix("export ")
+            .map(str::trim_start)
+            .unwrap_or(line);
+        let (name, raw) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected `NAME=value`", number))?;
+        let name = name.trim();
+        if !is_valid_name(name) {
+            return Err(format!("line {}: invalid variable name `{}`", number, name));
+        }
+
+        let value = {
+            let known = |key: &str| {
+                entries
+                    .iter()
+                    .rev()
+                    .find(|(existing, _)| existing == key)
+                    .map(|(_, value)| value.clone())
+                    .or_else(|| lookup(key))
+            };
+            let raw = raw.trim_start();
+            if let Some(rest) = raw.strip_prefix('\'') {
+                let (value, rest) = rest
+                    .split_once('\'')
+                    .ok_or_else(|| format!("line {}: unterminated single quoted value", number))?;
+                check_trailing(rest, number)?;
+                value.to_string()
+            } else if let Some(rest) = raw.strip_prefix('"') {
+                let (value, rest) = read_double_quoted(rest, &mut lines)
+                    .ok_or_else(|| format!("line {}: unterminated double quoted value", number))?;
+                check_trailing(rest, number)?;
+                interpolate(&value, &known).map_err(|e| format!("line {}: {}", number, e))?
+            } else {
+                let value = match raw.find(" #") {
+                    Some(comment) => &raw[..comment],
+                    None => raw,
+                };
+                interpolate(value.trim_end(), &known)
+                    .map_err(|e| format!("line {}: {}", number, e))?
+            }
+        };
+        entries.push((name.to_string(), value));
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        (name == "HOME").then(|| "/home/app".to_string())
+    }
+
+    #[test]
+    fn test_parse_dotenv() {
+        let content = r#"
+# Deployment settings
+export API_URL=https://api.example.com # production
+DATA_DIR=${HOME}/data
+CACHE_DIR="${DATA_DIR}/cache"
+PASSWORD='pa$$w#rd'
+GREETING="Hello\n\"world\""
+CERT="-----BEGIN-----
+abc
+-----END-----"
+EMPTY=
+"#;
+        let entries = parse(content, &lookup).unwrap();
+        let expected = [
+            ("API_URL", "https://api.example.com"),
+            ("DATA_DIR", "/home/app/data"),
+            ("CACHE_DIR", "/home/app/data/cache"),
+            ("PASSWORD", "pa$$w#rd"),
+            ("GREETING", "Hello\n\"world\""),
+            ("CERT", "-----BEGIN-----\nabc\n-----END-----"),
+            ("EMPTY", ""),
+        ];
+        assert_eq!(
+            entries,
+            expected
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_parse_dotenv_errors() {
+        assert!(parse("NO_VALUE\n", &lookup).unwrap_err().contains("line 1"));
+        assert!(
+            parse("A=1\nBAD NAME=2\n", &lookup)
+                .unwrap_err()
+                .contains("line 2: invalid variable name")
+        );
+        assert!(
+            parse("A=\"open\n", &lookup)
+                .unwrap_err()
+                .contains("unterminated double quoted value")
+        );
+        assert!(parse("A='x' y\n", &lookup).is_err());
+    }
+}
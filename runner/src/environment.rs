@@ -0,0 +1,342 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use shared::config::{EnvValue, ProjectConfig};
+use shared::debug_println;
+
+#[derive(Debug, PartialEq)]
+pub enum EnvChange {
+    Set(String, String),
+    Unset(String),
+}
+
+/// Values the runner exports to the app and makes available to `[env]` interpolation.
+pub fn runtime_variables(project_root: &Path, profile: Option<&str>) -> Vec<(String, String)> {
+    let mut variables = vec![(
+        "PYCRUCIBLE_PROJECT_DIR".to_string(),
+        project_root.display().to_string(),
+    )];
+    if let Ok(exe) = std::env::current_exe() {
+        variables.push(("PYCRUCIBLE_EXE".to_string(), exe.display().to_string()));
+        if let Some(exe_dir) = exe.parent() {
+            variables.push((
+                "PYCRUCIBLE_EXE_DIR".to_string(),
+                exe_dir.display().to_string(),
+            ));
+        }
+    }
+    if let Some(config_dir) = dirs::config_dir() {
+        variables.push((
+            "PYCRUCIBLE_CONFIG_DIR".to_string(),
+            config_dir.display().to_string(),
+        ));
+    }
+    if let Some(profile) = profile {
+        variables.push(("PYCRUCIBLE_PROFILE".to_string(), profile.to_string()));
+    }
+    variables
+}
+
+// Index of the `}` closing a `${` whose content starts at `start`, nested `${...}` included
+fn closing_brace(input: &str, start: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in input[start..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(start + i),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Expands `${VAR}` and `${VAR:-default}` (used when `VAR` is unset or empty), `$$` is a literal `$`.
+pub fn interpolate(input: &str, lookup: &dyn Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(position) = rest.find('$') {
+        output.push_str(&rest[..position]);
+        let after = &rest[position + 1..];
+        if let Some(after_dollar) = after.strip_prefix('$') {
+            output.push('$');
+            rest = after_dollar;
+        } else if after.starts_with('{') {
+            let end = closing_brace(after, 1)
+                .ok_or_else(|| format!("Unterminated `${{` in `{}`", input))?;
+            let expression = &after[1..end];
+            let (name, default) = match expression.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (expression, None),
+            };
+            if name.is_empty() {
+                return Err(format!("Empty variable name in `{}`", input));
+            }
+            match (lookup(name).filter(|value| !value.is_empty()), default) {
+                (Some(value), _) => output.push_str(&value),
+                (None, Some(default)) => output.push_str(&interpolate(default, lookup)?),
+                (None, None) => {
+                    debug_println!(
+                        "[environment.interpolate] - `{}` is not set, using an empty value",
+                        name
+                    );
+                }
+            }
+            rest = &after[end + 1..];
+        } else {
+            output.push('$');
+            rest = after;
+        }
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Works out every change `[env]` makes. Values see the process environment once `env_files`,
+/// the inherited variables and the `PYCRUCIBLE_*` variables are applied (in increasing
+/// precedence), but not each other, so the order of `[env]` does not matter.
+pub fn resolve_env(
+    variables: &HashMap<String, EnvValue>,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<Vec<EnvChange>, String> {
+    let mut names: Vec<&String> = variables.keys().collect();
+    names.sort();
+
+    let mut changes = Vec::with_capacity(names.len());
+    for name in names {
+        let expand = |value: &str| {
+            interpolate(value, lookup).map_err(|e| format!("Invalid value for `{}`: {}", name, e))
+        };
+        match &variables[name] {
+            EnvValue::Value(value) => changes.push(EnvChange::Set(name.clone(), expand(value)?)),
+            EnvValue::Entry(entry) if entry.unset => changes.push(EnvChange::Unset(name.clone())),
+            EnvValue::Entry(entry) => {
+                let separator = entry
+                    .separator
+                    .clone()
+                    .unwrap_or_else(|| if cfg!(windows) { ";" } else { ":" }.to_string());
+                let base = match &entry.value {
+                    Some(value) => Some(expand(value)?),
+                    None => lookup(name),
+                };
+                let mut parts = Vec::new();
+                for item in &entry.prepend {
+                    parts.push(expand(item)?);
+                }
+                parts.extend(base.filter(|base| !base.is_empty()));
+                for item in &entry.append {
+                    parts.push(expand(item)?);
+                }
+                changes.push(EnvChange::Set(name.clone(), parts.join(&separator)));
+            }
+        }
+    }
+    Ok(changes)
+}
+
+fn lookup_process_env(name: &str) -> Option<String> {
+    match std::env::var(name) {
+        Ok(value) => Some(value),
+        // `${HOME}` should work on Windows too
+        Err(_) if name == "HOME" => std::env::var("USERPROFILE").ok(),
+        Err(_) => None,
+    }
+}
+
+// Needed to start uv and Python at all, kept even with `clear_env`
+const SYSTEM_VARIABLES: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    "SYSTEMROOT",
+    "SYSTEMDRIVE",
+    "WINDIR",
+    "COMSPEC",
+    "PATHEXT",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+];
+
+fn clear_inherited_env() {
+    for (name, _) in std::env::vars_os() {
+        let keep = name.to_str().is_some_and(|name| {
+            SYSTEM_VARIABLES
+                .iter()
+                .any(|system| system.eq_ignore_ascii_case(name))
+        });
+        if !keep {
+            unsafe { std::env::remove_var(&name) };
+        }
+    }
+}
+
+/// Interpolates a path from the configuration, relative paths are resolved against the directory
+/// of the binary rather than the working directory.
+pub fn resolve_runtime_path(path: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(interpolate(path, &lookup_process_env)?);
+    if path.is_absolute() {
+        return Ok(path);
+    }
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+    Ok(exe_dir.join(path))
+}
+
+// Applies `env_files` in order, a later file overrides an earlier one but never a `protected`
+// (inherited or runtime) variable. Missing files are skipped.
+fn apply_env_files(env_files: &[String], protected: &HashSet<String>) -> io::Result<()> {
+    for env_file in env_files {
+        let path = resolve_runtime_path(env_file)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        if !path.is_file() {
+            debug_println!(
+                "[environment.apply_env_files] - {} not found, skipping",
+                path.display()
+            );
+            continue;
+        }
+        let content = std::fs::read_to_string(&path)?;
+        let entries = crate::dotenv::parse(&content, &lookup_process_env).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid env file {}: {}", path.display(), e),
+            )
+        })?;
+        for (name, value) in entries {
+            if protected.contains(&name) {
+                debug_println!(
+                    "[environment.apply_env_files] - {} is already set, ignoring it in {}",
+                    name,
+                    path.display()
+                );
+                continue;
+            }
+            unsafe { std::env::set_var(name, value) };
+        }
+    }
+    Ok(())
+}
+
+/// Prepares the environment uv and the app inherit, in increasing precedence: `env_files`, the
+/// parent environment (unless `clear_env` is set), the `PYCRUCIBLE_*` variables and `[env]`.
+pub fn apply_env_from_config(config: &ProjectConfig, project_root: &Path) -> io::Result<()> {
+    let mut protected: HashSet<String> = if config.options.clear_env {
+        clear_inherited_env();
+        HashSet::new()
+    } else {
+        std::env::vars_os()
+            .filter_map(|(name, _)| name.into_string().ok())
+            .collect()
+    };
+    for (name, value) in runtime_variables(project_root, config.profile.as_deref()) {
+        unsafe { std::env::set_var(&name, value) }; // Set env variables - not thread safe
+        protected.insert(name);
+    }
+    apply_env_files(&config.options.env_files, &protected)?;
+
+    let Some(variables) = config.env.as_ref().and_then(|env| env.variables.as_ref()) else {
+        return Ok(());
+    };
+    let changes = resolve_env(variables, &lookup_process_env)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    for change in changes {
+        debug_println!("[environment.apply_env_from_config] - {:?}", change);
+        match change {
+            EnvChange::Set(name, value) => unsafe { std::env::set_var(name, value) },
+            EnvChange::Unset(name) => unsafe { std::env::remove_var(name) },
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::config::EnvEntry;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "PYCRUCIBLE_PROJECT_DIR" => Some("/opt/app".to_string()),
+            "PATH" => Some("/usr/bin".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_interpolate() {
+        assert_eq!(
+            interpolate("${PYCRUCIBLE_PROJECT_DIR}/src", &lookup).unwrap(),
+            "/opt/app/src"
+        );
+        assert_eq!(
+            interpolate("${MISSING:-fallback}", &lookup).unwrap(),
+            "fallback"
+        );
+        assert_eq!(
+            interpolate("${EMPTY:-${PYCRUCIBLE_PROJECT_DIR}/data}", &lookup).unwrap(),
+            "/opt/app/data"
+        );
+        assert_eq!(interpolate("${MISSING}", &lookup).unwrap(), "");
+        assert_eq!(
+            interpolate("cost: $$5, $PATH", &lookup).unwrap(),
+            "cost: $5, $PATH"
+        );
+        assert!(interpolate("${PYCRUCIBLE_PROJECT_DIR", &lookup).is_err());
+    }
+
+    #[test]
+    fn test_resolve_env() {
+        let variables = HashMap::from([
+            (
+                "PYTHONPATH".to_string(),
+                EnvValue::Value("${PYCRUCIBLE_PROJECT_DIR}/src".to_string()),
+            ),
+            (
+                "PATH".to_string(),
+                EnvValue::Entry(EnvEntry {
+                    prepend: vec!["${PYCRUCIBLE_PROJECT_DIR}/bin".to_string()],
+                    append: vec!["/extra".to_string()],
+                    separator: Some(":".to_string()),
+                    ..Default::default()
+                }),
+            ),
+            (
+                "NEW_LIST".to_string(),
+                EnvValue::Entry(EnvEntry {
+                    append: vec!["a".to_string(), "b".to_string()],
+                    separator: Some(",".to_string()),
+                    ..Default::default()
+                }),
+            ),
+            (
+                "DEBUG".to_string(),
+                EnvValue::Entry(EnvEntry {
+                    unset: true,
+                    ..Default::default()
+                }),
+            ),
+        ]);
+
+        let changes = resolve_env(&variables, &lookup).unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                EnvChange::Unset("DEBUG".to_string()),
+                EnvChange::Set("NEW_LIST".to_string(), "a,b".to_string()),
+                EnvChange::Set(
+                    "PATH".to_string(),
+                    "/opt/app/bin:/usr/bin:/extra".to_string()
+                ),
+                EnvChange::Set("PYTHONPATH".to_string(), "/opt/app/src".to_string()),
+            ]
+        );
+    }
+}
@@ -2,7 +2,7 @@ use std::fs;
 use std::io::{self, Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
 
-use crate::repository::RepositoryHandler;
+use crate::repository::{RepositoryError, RepositoryHandler, SOURCE_CHECKOUT_DIR};
 use shared::config::load_project_config;
 use shared::debug_println;
 use shared::footer::PayloadInfo;
@@ -103,16 +103,35 @@ pub fn prepare_and_extract_payload() -> Option<PathBuf> {
     // Check for source configuration and update if necessary
     let pycrucibletoml_path = project_dir.join("pycrucible.toml");
     if pycrucibletoml_path.exists() {
-        let project_config = load_project_config(&project_dir.to_path_buf());
+        let project_config = match load_project_config(&project_dir) {
+            Ok(project_config) => project_config,
+            Err(e) => {
+                eprintln!("{}", e);
+                return None;
+            }
+        };
         if let Some(source_config) = &project_config.source {
             let sp = shared::spinner::create_spinner_with_message(
                 "Updating source code from repository...",
             );
             let mut repo_handler = RepositoryHandler::new(source_config.clone());
 
-            match repo_handler.init_or_open(&project_dir) {
-                Ok(_) => {
-                    if let Err(e) = repo_handler.update() {
+            match repo_handler.init_or_open(&project_dir.join(SOURCE_CHECKOUT_DIR)) {
+                Ok(_) => match repo_handler.update() {
+                    Ok(_) => {
+                        shared::spinner::stop_and_persist_spinner_with_message(
+                            sp,
+                            "Repository updated successfully",
+                        );
+                    }
+                    Err(RepositoryError::UntrustedRevision(reason)) => {
+                        shared::spinner::stop_and_persist_spinner_with_message(
+                            sp,
+                            "Update rejected, keeping current revision",
+                        );
+                        eprintln!("Refusing to update to untrusted revision: {}", reason);
+                    }
+                    Err(e) => {
                         shared::spinner::stop_and_persist_spinner_with_message(
                             sp,
                             "Failed to update repository",
@@ -120,11 +139,14 @@ pub fn prepare_and_extract_payload() -> Option<PathBuf> {
                         eprintln!("Error updating repository: {:?}", e);
                         std::process::exit(1);
                     }
-
+                },
+                Err(RepositoryError::UntrustedRevision(reason)) => {
                     shared::spinner::stop_and_persist_spinner_with_message(
                         sp,
-                        "Repository updated successfully",
+                        "Failed to initialize repository",
                     );
+                    eprintln!("Refusing to run untrusted revision: {}", reason);
+                    std::process::exit(1);
                 }
                 Err(e) => {
                     shared::spinner::stop_and_persist_spinner_with_message(
@@ -132,6 +154,8 @@ pub fn prepare_and_extract_payload() -> Option<PathBuf> {
                         "Failed to initialize repository",
                     );
                     eprintln!("Error initializing repository: {:?}", e);
+                    // There is no checkout to run
+                    std::process::exit(1);
                 }
             }
         }
@@ -0,0 +1,308 @@
+use std::io;
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus};
+use std::time::{Duration, Instant};
+
+use shared::config::{HookKind, HookList, HookSpec};
+use shared::debug_println;
+
+use crate::run::uv_run_command;
+
+/// Where and how hooks run.
+#[derive(Clone, Copy)]
+pub struct HookContext<'a> {
+    pub uv_path: &'a Path,
+    pub project_root: &'a Path,
+    pub lock_flag: Option<&'a str>,
+    // Exit code of the app, exported as `PYCRUCIBLE_EXIT_CODE` to `on_error` and `post_run`
+    pub exit_code: Option<i32>,
+    // Exported as `PYCRUCIBLE_SETUP_MESSAGE` to `on_setup`
+    pub setup_message: Option<&'a str>,
+}
+
+fn describe(kind: &HookKind) -> String {
+    match kind {
+        HookKind::Script(script) => format!("script `{}`", script),
+        HookKind::Module(module) => format!("module `{}`", module),
+        HookKind::Command(command) => format!("command `{}`", command),
+    }
+}
+
+fn hook_command(kind: &HookKind, hook: &HookSpec, context: &HookContext) -> Command {
+    let mut command = match kind {
+        HookKind::Script(script) => {
+            let mut command = uv_run_command(
+                context.uv_path,
+                context.project_root,
+                context.lock_flag,
+                &[],
+            );
+            command.arg(context.project_root.join(script));
+            command
+        }
+        HookKind::Module(module) => {
+            let mut command = uv_run_command(
+                context.uv_path,
+                context.project_root,
+                context.lock_flag,
+                &[],
+            );
+            command.args(["python", "-m", module]);
+            command
+        }
+        HookKind::Command(program) => {
+            let mut command = Command::new(program);
+            command.current_dir(context.project_root);
+            command
+        }
+    };
+    command.args(hook.args());
+    if let Some(exit_code) = context.exit_code {
+        command.env("PYCRUCIBLE_EXIT_CODE", exit_code.to_string());
+    }
+    if let Some(message) = context.setup_message {
+        command.env("PYCRUCIBLE_SETUP_MESSAGE", message);
+    }
+    command
+}
+
+// A hook and everything it started: its own process group on Unix, a job object on Windows.
+// `uv run` starts Python as a child, killing only `uv` would leave the hook running.
+struct ProcessTree {
+    child: Child,
+    #[cfg(windows)]
+    job: Option<windows_sys::Win32::Foundation::HANDLE>,
+}
+
+impl ProcessTree {
+    #[cfg(unix)]
+    fn spawn(command: &mut Command) -> io::Result<Self> {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+        Ok(Self {
+            child: command.spawn()?,
+        })
+    }
+
+    #[cfg(windows)]
+    fn spawn(command: &mut Command) -> io::Result<Self> {
+        use std::os::windows::io::AsRawHandle;
+        use windows_sys::Win32::Foundation::CloseHandle;
+        use windows_sys::Win32::System::JobObjects::{AssignProcessToJobObject, CreateJobObjectW};
+
+        let child = command.spawn()?;
+        // Without a job the hook itself is still killed, just not what it started
+        let job = unsafe {
+            let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+            if job.is_null() {
+                None
+            } else if AssignProcessToJobObject(job, child.as_raw_handle() as _) == 0 {
+                CloseHandle(job);
+                None
+            } else {
+                Some(job)
+            }
+        };
+        Ok(Self { child, job })
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn spawn(command: &mut Command) -> io::Result<Self> {
+        Ok(Self {
+            child: command.spawn()?,
+        })
+    }
+
+    #[cfg(unix)]
+    fn kill(&mut self) -> io::Result<()> {
+        // The group id is the pid of the hook, a negative pid signals the whole group
+        let group = self.child.id() as libc::pid_t;
+        if unsafe { libc::kill(-group, libc::SIGKILL) } == 0 {
+            return Ok(());
+        }
+        self.child.kill()
+    }
+
+    #[cfg(windows)]
+    fn kill(&mut self) -> io::Result<()> {
+        use windows_sys::Win32::System::JobObjects::TerminateJobObject;
+        if let Some(job) = self.job
+            && unsafe { TerminateJobObject(job, 1) } != 0
+        {
+            return Ok(());
+        }
+        self.child.kill()
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn kill(&mut self) -> io::Result<()> {
+        self.child.kill()
+    }
+}
+
+#[cfg(windows)]
+impl Drop for ProcessTree {
+    fn drop(&mut self) {
+        if let Some(job) = self.job {
+            unsafe { windows_sys::Win32::Foundation::CloseHandle(job) };
+        }
+    }
+}
+
+// Waits for `command`, killing it and everything it started once `timeout` has passed
+fn run_with_timeout(mut command: Command, timeout: Option<Duration>) -> io::Result<ExitStatus> {
+    // Only hooks with a timeout get a process group of their own, the others keep receiving
+    // Ctrl+C from the terminal like the app does
+    let Some(timeout) = timeout else {
+        return command.status();
+    };
+    let mut tree = ProcessTree::spawn(&mut command)?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = tree.child.try_wait()? {
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            tree.kill()?;
+            tree.child.wait()?;
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("timed out after {}s", timeout.as_secs()),
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Runs the hooks of `point` in order. A failing hook stops the ones after it and is returned
+/// as an error, unless it is marked `continue_on_error`.
+pub fn run_hooks(point: &str, hooks: Option<&HookList>, context: &HookContext) -> io::Result<()> {
+    let Some(hooks) = hooks else {
+        return Ok(());
+    };
+    for hook in hooks.hooks() {
+        let kind = hook.kind().map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} hook: {}", point, e),
+            )
+        })?;
+        // `pre_run = ""` has always meant no hook
+        if kind == HookKind::Script("") {
+            continue;
+        }
+
+        let mut command = hook_command(&kind, hook, context);
+        command.env("PYCRUCIBLE_HOOK", point);
+        debug_println!("[hooks.run_hooks] - Running {} hook: {:?}", point, command);
+
+        let timeout = hook.timeout().map(Duration::from_secs);
+        let result = run_with_timeout(command, timeout).and_then(|status| {
+            if status.success() {
+                Ok(())
+            } else {
+                Err(io::Error::other(format!("exited with {}", status)))
+            }
+        });
+        if let Err(e) = result {
+            let message = format!("{} hook {} failed: {}", point, describe(&kind), e);
+            if hook.continue_on_error() {
+                eprintln!("Warning: {}", message);
+            } else {
+                return Err(io::Error::new(e.kind(), message));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use shared::config::HookEntry;
+    use tempfile::tempdir;
+
+    fn shell(script: &str) -> HookSpec {
+        HookSpec::Entry(HookEntry {
+            command: Some("sh".to_string()),
+            args: vec!["-c".to_string(), script.to_string()],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_run_hooks_in_order_with_exit_code() {
+        let dir = tempdir().unwrap();
+        let hooks = HookList::Many(vec![
+            shell("echo \"$PYCRUCIBLE_HOOK $PYCRUCIBLE_EXIT_CODE\" > first"),
+            shell("cat first > second"),
+        ]);
+        let context = HookContext {
+            uv_path: Path::new("uv"),
+            project_root: dir.path(),
+            lock_flag: None,
+            exit_code: Some(3),
+            setup_message: None,
+        };
+
+        run_hooks("post_run", Some(&hooks), &context).unwrap();
+        let output = std::fs::read_to_string(dir.path().join("second")).unwrap();
+        assert_eq!(output.trim(), "post_run 3");
+    }
+
+    #[test]
+    fn test_run_hooks_failures() {
+        let dir = tempdir().unwrap();
+        let context = HookContext {
+            uv_path: Path::new("uv"),
+            project_root: dir.path(),
+            lock_flag: None,
+            exit_code: None,
+            setup_message: None,
+        };
+
+        let failing = HookList::Many(vec![shell("exit 2"), shell("touch after")]);
+        let err = run_hooks("pre_run", Some(&failing), &context).unwrap_err();
+        assert!(err.to_string().contains("pre_run hook command `sh` failed"));
+        assert!(!dir.path().join("after").exists());
+
+        let HookSpec::Entry(mut tolerated) = shell("exit 2") else {
+            unreachable!()
+        };
+        tolerated.continue_on_error = true;
+        let tolerated = HookList::Many(vec![HookSpec::Entry(tolerated), shell("touch after")]);
+        run_hooks("pre_run", Some(&tolerated), &context).unwrap();
+        assert!(dir.path().join("after").exists());
+
+        let HookSpec::Entry(mut slow) = shell("sleep 5") else {
+            unreachable!()
+        };
+        slow.timeout = Some(0);
+        let slow = HookList::One(HookSpec::Entry(slow));
+        let err = run_hooks("pre_run", Some(&slow), &context).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_timeout_kills_what_the_hook_started() {
+        let dir = tempdir().unwrap();
+        let context = HookContext {
+            uv_path: Path::new("uv"),
+            project_root: dir.path(),
+            lock_flag: None,
+            exit_code: None,
+            setup_message: None,
+        };
+
+        // Like `uv run`, the shell waits on a child doing the actual work
+        let HookSpec::Entry(mut slow) = shell("(sleep 2; touch late) & wait") else {
+            unreachable!()
+        };
+        slow.timeout = Some(1);
+        let slow = HookList::One(HookSpec::Entry(slow));
+        let err = run_hooks("pre_run", Some(&slow), &context).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        std::thread::sleep(Duration::from_millis(2500));
+        assert!(!dir.path().join("late").exists());
+    }
+}
@@ -1,11 +1,20 @@
+mod dispatch;
+mod dotenv;
+mod environment;
 mod extract;
+mod hooks;
 mod repository;
 mod run;
+mod setup;
+mod signature;
+mod state;
 
 use std::{env, io};
 
 fn main() -> io::Result<()> {
-    let runtime_args: Vec<String> = env::args().skip(1).collect();
+    let mut args = env::args();
+    let argv0 = args.next();
+    let runtime_args: Vec<String> = args.collect();
 
     let path = extract::prepare_and_extract_payload();
     if path.is_none() {
@@ -14,6 +23,6 @@ fn main() -> io::Result<()> {
     }
     let project_dir = path.unwrap();
 
-    run::run_extracted_project(&project_dir, runtime_args)?;
-    Ok(())
+    let exit_code = run::run_extracted_project(&project_dir, argv0.as_deref(), runtime_args)?;
+    std::process::exit(exit_code);
 }
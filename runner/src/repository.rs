@@ -1,8 +1,13 @@
-use git2::{FetchOptions, Repository};
+use crate::signature;
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use git2::{FetchOptions, Oid, Repository, SubmoduleUpdateOptions};
 use shared::config::SourceConfig;
 use shared::debug_println;
 use std::path::Path;
 
+/// Directory inside the extracted payload where source mode checkouts live.
+pub const SOURCE_CHECKOUT_DIR: &str = "source";
+
 pub struct RepositoryHandler {
     repo: Option<Repository>,
     config: SourceConfig,
@@ -13,6 +18,8 @@ pub struct RepositoryHandler {
 pub enum RepositoryError {
     GitError(git2::Error),
     InvalidConfiguration(&'static str),
+    UntrustedRevision(String),
+    IoError(std::io::Error),
 }
 
 impl From<git2::Error> for RepositoryError {
@@ -21,11 +28,82 @@ impl From<git2::Error> for RepositoryError {
     }
 }
 
+impl From<std::io::Error> for RepositoryError {
+    fn from(err: std::io::Error) -> Self {
+        RepositoryError::IoError(err)
+    }
+}
+
 impl RepositoryHandler {
     pub fn new(config: SourceConfig) -> Self {
         RepositoryHandler { repo: None, config }
     }
 
+    fn trusted_signers(&self) -> Option<&Vec<String>> {
+        self.config
+            .trusted_signers
+            .as_ref()
+            .filter(|signers| !signers.is_empty())
+    }
+
+    fn verify_commit(&self, repo: &Repository, oid: Oid) -> Result<(), RepositoryError> {
+        match self.trusted_signers() {
+            Some(signers) => signature::verify_commit(repo, oid, signers)
+                .map_err(RepositoryError::UntrustedRevision),
+            None => Ok(()),
+        }
+    }
+
+    fn verify_tag(&self, repo: &Repository, oid: Oid) -> Result<(), RepositoryError> {
+        match self.trusted_signers() {
+            Some(signers) => signature::verify_tag(repo, oid, signers)
+                .map_err(RepositoryError::UntrustedRevision),
+            None => Ok(()),
+        }
+    }
+
+    fn fetch_options(&self) -> FetchOptions<'static> {
+        let mut fetch_opts = FetchOptions::new();
+        if let Some(depth) = self.config.depth {
+            fetch_opts.depth(depth);
+        }
+        fetch_opts
+    }
+
+    /// Restricts checkout to the configured subdirectory and sparse paths, if any.
+    fn checkout_builder(&self) -> CheckoutBuilder<'static> {
+        let mut checkout = CheckoutBuilder::new();
+        checkout.force();
+        if let Some(sparse_paths) = &self.config.sparse_paths {
+            for sparse_path in sparse_paths {
+                checkout.path(sparse_path);
+            }
+            if let Some(subdirectory) = &self.config.subdirectory {
+                checkout.path(subdirectory);
+            }
+        }
+        checkout
+    }
+
+    fn update_submodules(&self, repo: &Repository) -> Result<(), RepositoryError> {
+        if !self.config.submodules.unwrap_or(false) {
+            return Ok(());
+        }
+        for mut submodule in repo.submodules()? {
+            debug_println!(
+                "[repository.update_submodules] - Updating submodule {}",
+                submodule.path().display()
+            );
+            let mut update_opts = SubmoduleUpdateOptions::new();
+            update_opts.fetch(self.fetch_options());
+            submodule.update(true, Some(&mut update_opts))?;
+            // Recurse into nested submodules
+            let submodule_repo = submodule.open()?;
+            self.update_submodules(&submodule_repo)?;
+        }
+        Ok(())
+    }
+
     pub fn init_or_open(&mut self, path: &Path) -> Result<(), RepositoryError> {
         self.repo = if path.join(".git").exists() {
             debug_println!(
@@ -39,41 +117,29 @@ impl RepositoryHandler {
                 path.display(),
                 self.config.repository
             );
+            // The checkout lives in its own directory, so a leftover from an
+            // interrupted clone can be removed without touching the payload
             if path.exists() {
-                let uv_name = if cfg!(windows) { "uv.exe" } else { "uv" };
-                let uv_path = path.join(uv_name);
-                let temp_uv_path = std::env::temp_dir().join(uv_name);
-
-                // Move uv to temp if it exists
-                if uv_path.exists() {
-                    std::fs::rename(&uv_path, &temp_uv_path).expect("Failed to move uv to temp");
-                }
-
-                // Clear directory
-                let entries = std::fs::read_dir(path).expect("Failed to read directory");
-                for entry in entries.flatten() {
-                    let path = entry.path();
-                    if path.is_dir() {
-                        std::fs::remove_dir_all(path).expect("Failed to remove directory");
-                    } else {
-                        std::fs::remove_file(path).expect("Failed to remove file");
-                    }
-                }
+                std::fs::remove_dir_all(path)?;
             }
-            let repo = Some(Repository::clone(&self.config.repository, path)?);
-
-            // Move uv back if it was temporarily moved
-            let temp_uv_path =
-                std::env::temp_dir().join(if cfg!(windows) { "uv.exe" } else { "uv" });
-            if temp_uv_path.exists() {
-                std::fs::rename(
-                    &temp_uv_path,
-                    path.join(if cfg!(windows) { "uv.exe" } else { "uv" }),
-                )
-                .expect("Failed to move uv back");
+            let mut builder = RepoBuilder::new();
+            builder
+                .fetch_options(self.fetch_options())
+                .with_checkout(self.checkout_builder());
+            if let Some(branch) = &self.config.branch {
+                builder.branch(branch);
             }
+            let cloned = builder.clone(&self.config.repository, path)?;
 
-            repo
+            // There is no previous revision to fall back to, so a fresh clone must be trusted
+            let head_id = cloned.head()?.peel_to_commit()?.id();
+            if let Err(e) = self.verify_commit(&cloned, head_id) {
+                drop(cloned);
+                std::fs::remove_dir_all(path)?;
+                return Err(e);
+            }
+            self.update_submodules(&cloned)?;
+            Some(cloned)
         };
         Ok(())
     }
@@ -91,7 +157,7 @@ impl RepositoryHandler {
 
         match strategy {
             "pull" => {
-                let mut fetch_opts = FetchOptions::new();
+                let mut fetch_opts = self.fetch_options();
                 remote.fetch(&[] as &[&str], Some(&mut fetch_opts), None)?;
 
                 // Get the latest commit from the remote branch
@@ -99,13 +165,14 @@ impl RepositoryHandler {
                 let remote_branch =
                     repo.find_branch(&format!("origin/{}", branch_name), git2::BranchType::Remote)?;
                 let remote_commit = remote_branch.get().peel_to_commit()?;
+                self.verify_commit(repo, remote_commit.id())?;
 
                 // Get the current HEAD and set it to the remote commit
                 let mut head = repo.head()?;
                 head.set_target(remote_commit.id(), "pull: Fast-forward update")?;
             }
             "fetch" => {
-                let mut fetch_opts = FetchOptions::new();
+                let mut fetch_opts = self.fetch_options();
                 remote.fetch(&[] as &[&str], Some(&mut fetch_opts), None)?;
             }
             _ => {
@@ -119,13 +186,204 @@ impl RepositoryHandler {
         if let Some(tag) = &self.config.tag {
             let tag_oid = git2::Oid::from_str(tag)?;
             let tag_ref = repo.find_tag(tag_oid)?;
+            self.verify_tag(repo, tag_ref.id())?;
             repo.set_head_detached(tag_ref.target_id())?;
         } else if let Some(commit) = &self.config.commit {
             let oid = git2::Oid::from_str(commit)?;
             let commit_obj = repo.find_commit(oid)?;
+            self.verify_commit(repo, commit_obj.id())?;
             repo.set_head_detached(commit_obj.id())?;
         }
 
+        repo.checkout_head(Some(&mut self.checkout_builder()))?;
+        self.update_submodules(repo)?;
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn create_origin(path: &Path) -> Repository {
+        let repo = Repository::init(path).unwrap();
+        for file in ["app/main.py", "shared/lib.py", "docs/index.md"] {
+            let file_path = path.join(file);
+            fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+            fs::write(file_path, b"# content").unwrap();
+        }
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        let tree_id = index.write_tree().unwrap();
+        {
+            let tree = repo.find_tree(tree_id).unwrap();
+            let sig = git2::Signature::now("test", "test@example.com").unwrap();
+            repo.commit(Some("HEAD"), &sig, &sig, "initial", &tree, &[])
+                .unwrap();
+        }
+        repo
+    }
+
+    #[test]
+    fn test_sparse_clone_checks_out_subdirectory_only() {
+        let origin_dir = tempdir().unwrap();
+        create_origin(origin_dir.path());
+
+        let checkout_dir = tempdir().unwrap();
+        let checkout = checkout_dir.path().join("checkout");
+        let config = SourceConfig {
+            repository: format!("file://{}", origin_dir.path().display()),
+            subdirectory: Some("app".to_string()),
+            sparse_paths: Some(vec!["shared".to_string()]),
+            ..Default::default()
+        };
+
+        let mut handler = RepositoryHandler::new(config);
+        handler.init_or_open(&checkout).unwrap();
+
+        assert!(checkout.join("app/main.py").exists());
+        assert!(checkout.join("shared/lib.py").exists());
+        assert!(!checkout.join("docs/index.md").exists());
+    }
+
+    #[test]
+    fn test_unsigned_clone_is_rejected_when_signers_are_trusted() {
+        let origin_dir = tempdir().unwrap();
+        create_origin(origin_dir.path());
+
+        let checkout_dir = tempdir().unwrap();
+        let checkout = checkout_dir.path().join("checkout");
+        let config = SourceConfig {
+            repository: format!("file://{}", origin_dir.path().display()),
+            trusted_signers: Some(vec![
+                "ssh-ed25519 AAAAC3Nza trusted@example.com".to_string(),
+            ]),
+            ..Default::default()
+        };
+
+        let mut handler = RepositoryHandler::new(config);
+        let result = handler.init_or_open(&checkout);
+        assert!(matches!(result, Err(RepositoryError::UntrustedRevision(_))));
+        assert!(!checkout.join("app/main.py").exists());
+    }
+
+    // Adds `file` to the current branch of `origin`, signed with `signature` if given
+    fn commit_to_origin(origin: &Repository, file: &str, signature: Option<&str>) -> Oid {
+        let workdir = origin.workdir().unwrap();
+        fs::write(workdir.join(file), b"# update").unwrap();
+        let mut index = origin.index().unwrap();
+        index.add_path(Path::new(file)).unwrap();
+        let tree = origin.find_tree(index.write_tree().unwrap()).unwrap();
+        index.write().unwrap();
+        let parent = origin.head().unwrap().peel_to_commit().unwrap();
+        let sig = git2::Signature::now("test", "test@example.com").unwrap();
+        let buffer = origin
+            .commit_create_buffer(&sig, &sig, file, &tree, &[&parent])
+            .unwrap();
+        let oid = match signature {
+            Some(signature) => origin
+                .commit_signed(buffer.as_str().unwrap(), signature, None)
+                .unwrap(),
+            None => origin
+                .odb()
+                .unwrap()
+                .write(git2::ObjectType::Commit, &buffer)
+                .unwrap(),
+        };
+        let branch = origin.head().unwrap().name().unwrap().to_string();
+        origin.reference(&branch, oid, true, file).unwrap();
+        oid
+    }
+
+    #[test]
+    fn test_update_rejects_untrusted_commits_and_keeps_head() {
+        let origin_dir = tempdir().unwrap();
+        let origin = create_origin(origin_dir.path());
+        let branch = origin.head().unwrap().shorthand().unwrap().to_string();
+
+        let checkout_dir = tempdir().unwrap();
+        let checkout = checkout_dir.path().join("checkout");
+        let mut config = SourceConfig {
+            repository: format!("file://{}", origin_dir.path().display()),
+            branch: Some(branch),
+            ..Default::default()
+        };
+        RepositoryHandler::new(config.clone())
+            .init_or_open(&checkout)
+            .unwrap();
+        let verified = Repository::open(&checkout)
+            .unwrap()
+            .head()
+            .unwrap()
+            .target()
+            .unwrap();
+
+        config.trusted_signers = Some(vec![
+            "ssh-ed25519 AAAAC3Nza trusted@example.com".to_string(),
+        ]);
+        let forged =
+            "-----BEGIN SSH SIGNATURE-----\nbm90IGEgc2lnbmF0dXJl\n-----END SSH SIGNATURE-----\n";
+        for (file, signature) in [("unsigned.py", None), ("untrusted.py", Some(forged))] {
+            let remote_commit = commit_to_origin(&origin, file, signature);
+
+            let mut handler = RepositoryHandler::new(config.clone());
+            handler.init_or_open(&checkout).unwrap();
+            let result = handler.update();
+            assert!(
+                matches!(result, Err(RepositoryError::UntrustedRevision(_))),
+                "{} was not rejected",
+                file
+            );
+
+            // The new commit was fetched but HEAD stays on the verified revision
+            let repo = Repository::open(&checkout).unwrap();
+            let fetched = repo
+                .find_branch(
+                    &format!("origin/{}", config.branch.as_deref().unwrap()),
+                    git2::BranchType::Remote,
+                )
+                .unwrap()
+                .get()
+                .target()
+                .unwrap();
+            assert_eq!(fetched, remote_commit);
+            assert_eq!(repo.head().unwrap().target().unwrap(), verified);
+            assert!(!checkout.join(file).exists());
+        }
+    }
+
+    #[test]
+    fn test_clone_keeps_payload_next_to_checkout() {
+        let origin_dir = tempdir().unwrap();
+        create_origin(origin_dir.path());
+
+        let project_dir = tempdir().unwrap();
+        fs::write(
+            project_dir.path().join("pycrucible.toml"),
+            b"entry = \"app/main.py\"",
+        )
+        .unwrap();
+        fs::write(project_dir.path().join("uv"), b"uv-binary").unwrap();
+        // Leftover from an interrupted clone
+        let checkout = project_dir.path().join(SOURCE_CHECKOUT_DIR);
+        fs::create_dir_all(&checkout).unwrap();
+        fs::write(checkout.join("partial"), b"").unwrap();
+
+        let config = SourceConfig {
+            repository: format!("file://{}", origin_dir.path().display()),
+            ..Default::default()
+        };
+        let mut handler = RepositoryHandler::new(config);
+        handler.init_or_open(&checkout).unwrap();
+
+        assert!(checkout.join("app/main.py").exists());
+        assert!(!checkout.join("partial").exists());
+        assert!(project_dir.path().join("pycrucible.toml").exists());
+        assert!(project_dir.path().join("uv").exists());
+    }
+}
@@ -6,119 +6,183 @@ use std::path::PathBuf;
 use std::process::Command;
 use std::{self, io};
 
-use shared::config::{ProjectConfig, load_project_config};
-
-#[derive(Debug)]
-enum RunMode {
-    Source,
-    Wheel,
-    App,
-}
-
-fn apply_env_from_config(config: &ProjectConfig) {
-    if let Some(env_config) = &config.env
-        && let Some(vars) = &env_config.variables
-    {
-        for (k, v) in vars {
-            unsafe { std::env::set_var(k, v) }; // Set env variables - not thread safe
-        }
+use crate::dispatch::{self, Selection};
+use crate::environment::apply_env_from_config;
+use crate::hooks::{HookContext, run_hooks};
+use crate::repository::SOURCE_CHECKOUT_DIR;
+use crate::setup::{configure_uv_locations, ensure_environment};
+use crate::state::{Revision, RunState, app_dir};
+use shared::config::{
+    EntrypointKind, Hooks, LockMode, ProjectConfig, WheelConfig, load_project_config,
+};
+
+// Hold uv to the embedded lockfile, if the project has one
+fn lock_flag(project_root: &Path, config: &ProjectConfig) -> Option<&'static str> {
+    if !project_root.join("uv.lock").exists() {
+        return None;
+    }
+    match config.options.lock_mode {
+        LockMode::Locked => Some("--locked"),
+        LockMode::Frozen => Some("--frozen"),
+        LockMode::Off => None,
     }
 }
 
-fn prepare_hooks(config: &ProjectConfig) -> (String, String) {
-    // Figure out if there is a hooks section in the config
-    // Borrow the hooks if present
-    let hooks = config.hooks.as_ref();
-
-    let (pre_hook, post_hook) = hooks
-        .map(|h| {
-            (
-                h.pre_run.clone().unwrap_or_default(),
-                h.post_run.clone().unwrap_or_default(),
-            )
-        })
-        .unwrap_or((String::new(), String::new()));
-
-    debug_println!(
-        "[main.run_extracted_project] - Prepared hooks - pre_hook: {}, post_hook: {}",
-        pre_hook,
-        post_hook
-    );
-    (pre_hook, post_hook)
-}
-
-fn run_hook(
-    hook_name: &str,
-    hook_cmd: &str,
+/// `uv run` for `project_dir`, held to the lockfile with `lock_flag`.
+pub fn uv_run_command(
     uv_path: &Path,
     project_dir: &Path,
-) -> io::Result<()> {
-    if hook_cmd.is_empty() {
-        return Ok(());
-    }
-
-    debug_println!("[main.run_extracted_project] - Running {}", hook_name);
-
-    let hook_path = project_dir.join(hook_cmd);
-    
-    let path_str = hook_path.to_str().ok_or_else(|| {
-        io::Error::new(
-            io::ErrorKind::InvalidInput,
-            format!("Invalid {} path", hook_name),
-        )
-    })?;
-
-    run_uv(uv_path, project_dir, &[], &[path_str])
-}
-
-
-fn run_uv(uv_path: &Path, project_dir: &Path, with: &[&str], args: &[&str]) -> io::Result<()> {
+    lock_flag: Option<&str>,
+    with: &[&str],
+) -> Command {
     let mut cmd = Command::new(uv_path);
     cmd.arg("run").arg("-q");
+    if let Some(flag) = lock_flag {
+        cmd.arg(flag);
+    }
 
     for w in with {
         cmd.arg("--with").arg(w);
     }
     cmd.arg("--project");
     cmd.arg(project_dir);
+    cmd
+}
+
+/// `uv sync` for `project_dir`, held to the lockfile with `lock_flag`.
+pub fn uv_sync_command(uv_path: &Path, project_dir: &Path, lock_flag: Option<&str>) -> Command {
+    let mut cmd = Command::new(uv_path);
+    cmd.arg("sync").arg("-q");
+    if let Some(flag) = lock_flag {
+        cmd.arg(flag);
+    }
+    cmd.arg("--project");
+    cmd.arg(project_dir);
+    cmd
+}
 
-    cmd.args(args);
+// Runs the app and returns its exit code
+fn run_uv(
+    uv_path: &Path,
+    project_dir: &Path,
+    lock_flag: Option<&str>,
+    with: &[&str],
+    args: &[&str],
+) -> io::Result<i32> {
+    let status = uv_run_command(uv_path, project_dir, lock_flag, with)
+        .args(args)
+        .status()?;
+    // Killed by a signal
+    Ok(status.code().unwrap_or(1))
+}
 
-    let status = cmd.status()?;
+// `python -c` code calling `module:function` the way a console script wrapper does. Both are
+// validated dotted identifiers, so they can be spliced into the code as they are.
+fn callable_code(module: &str, function: &str) -> String {
+    let program = module.split('.').next().unwrap_or(module);
+    format!(
+        "import sys, {module}; sys.argv[0] = '{program}'; sys.exit({module}.{function}())",
+        module = module,
+        program = program,
+        function = function
+    )
+}
 
-    if !status.success() {
-        return Err(io::Error::other("uv run failed"));
+// Runs `on_first_run` or `on_update` when this is the first run of the binary or its payload or
+// source checkout changed. The revision is only recorded once they succeeded, so they are retried.
+fn run_lifecycle_hooks(
+    state: &mut RunState,
+    revision: &Revision,
+    hooks: &Hooks,
+    context: &HookContext,
+) -> io::Result<()> {
+    match &state.revision {
+        None => run_hooks("on_first_run", hooks.on_first_run.as_ref(), context)?,
+        Some(previous) if previous != revision => {
+            run_hooks("on_update", hooks.on_update.as_ref(), context)?
+        }
+        Some(_) => return Ok(()),
     }
-
+    state.revision = Some(revision.clone());
     Ok(())
 }
 
-fn find_single_wheel(project_dir: &Path) -> io::Result<Option<PathBuf>> {
-    let mut wheel: Option<PathBuf> = None;
-
-    for entry in std::fs::read_dir(project_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-
-        if path.extension().and_then(|e| e.to_str()) == Some("whl") {
-            match &wheel {
-                None => wheel = Some(path),
-                Some(_) => {
-                    return Err(io::Error::new(
-                        io::ErrorKind::InvalidInput,
-                        "Multiple .whl files found in the project directory",
-                    ));
-                }
+// The distribution name is the first part of a wheel file name, `my_app-1.0-py3-none-any.whl`
+fn distribution_name(wheel_file: &str) -> &str {
+    wheel_file.split('-').next().unwrap_or(wheel_file)
+}
+
+fn file_url(path: &Path) -> String {
+    let path: String = path
+        .to_string_lossy()
+        .replace('\\', "/")
+        .chars()
+        .map(|c| match c {
+            ' ' => "%20".to_string(),
+            '#' => "%23".to_string(),
+            '%' => "%25".to_string(),
+            c => c.to_string(),
+        })
+        .collect();
+    if path.starts_with('/') {
+        format!("file://{}", path)
+    } else {
+        format!("file:///{}", path)
+    }
+}
+
+// `--with` requirements installing the embedded wheels into the ephemeral environment. Extras
+// need a direct reference, `my_app[cli] @ file:///...`, plain paths do for everything else.
+fn wheel_requirements(project_dir: &Path, wheel: &WheelConfig) -> Vec<String> {
+    let mut requirements = Vec::new();
+    if let Some(app) = &wheel.app {
+        let path = project_dir.join(app);
+        requirements.push(if wheel.extras.is_empty() {
+            path.to_string_lossy().into_owned()
+        } else {
+            format!(
+                "{}[{}] @ {}",
+                distribution_name(app),
+                wheel.extras.join(","),
+                file_url(&path)
+            )
+        });
+    }
+    requirements.extend(
+        wheel
+            .wheels
+            .iter()
+            .map(|dependency| project_dir.join(dependency).to_string_lossy().into_owned()),
+    );
+    requirements
+}
+
+// Source checkouts live next to the payload and may keep the Python project
+// in a subdirectory of the repository
+fn resolve_project_root(project_dir: &Path, config: &ProjectConfig) -> PathBuf {
+    match &config.source {
+        Some(source) => {
+            let checkout_dir = project_dir.join(SOURCE_CHECKOUT_DIR);
+            match &source.subdirectory {
+                Some(subdirectory) => checkout_dir.join(subdirectory),
+                None => checkout_dir,
             }
         }
+        None => project_dir.to_path_buf(),
     }
-
-    Ok(wheel)
 }
 
-pub fn run_extracted_project(project_dir: &Path, runtime_args: Vec<String>) -> io::Result<()> {
+/// Runs the extracted project with its hooks and returns the exit code of the app.
+/// `argv0` picks a named entrypoint when the binary is invoked through a link.
+pub fn run_extracted_project(
+    project_dir: &Path,
+    argv0: Option<&str>,
+    runtime_args: Vec<String>,
+) -> io::Result<i32> {
     // Load project configuration and determine entrypoint
-    let config = load_project_config(&project_dir.to_path_buf());
+    let config = load_project_config(project_dir)
+        .and_then(ProjectConfig::for_host)
+        .map_err(io::Error::other)?;
     debug_println!("[main.run_extracted_project] - Loaded project configuration");
 
     // Enable debug mode if specified in config
@@ -127,113 +191,257 @@ pub fn run_extracted_project(project_dir: &Path, runtime_args: Vec<String>) -> i
         debug_println!("[main.run_extracted_project] - Debug mode enabled");
     }
 
+    // Pick the command before anything slow happens, `--help` should be instant
+    let invoked_as = argv0.and_then(dispatch::invoked_name);
+    let (entrypoint, runtime_args) =
+        match dispatch::select(&config.package, invoked_as.as_deref(), runtime_args) {
+            Selection::Run { entrypoint, args } => (entrypoint, args),
+            Selection::Help { error } => {
+                let help = dispatch::help_text(
+                    invoked_as.as_deref().unwrap_or("pycrucible"),
+                    &config.package,
+                );
+                return match error {
+                    None => {
+                        print!("{}", help);
+                        Ok(0)
+                    }
+                    Some(error) => {
+                        eprintln!("error: {}\n\n{}", error, help);
+                        Ok(2)
+                    }
+                };
+            }
+        };
+
     // Ensure UV is available
     debug_println!("[main.run_extracted_project] - Ensuring UV is available");
-    let uv_path =
-        find_or_download_uv(None, config.options.uv_version.as_str()).ok_or(io::Error::new(
-            io::ErrorKind::NotFound,
-            "Could not find or download uv binary",
-        ))?;
+    let uv_path = find_or_download_uv(None, &config.options).ok_or(io::Error::new(
+        io::ErrorKind::NotFound,
+        "Could not find or download uv binary",
+    ))?;
+
+    let project_root = resolve_project_root(project_dir, &config);
 
     // Apply environment variables from config (unsafe but we are single-threaded so it should be fine)
-    apply_env_from_config(&config);
+    apply_env_from_config(&config, &project_root)?;
     debug_println!(
         "[main.run_extracted_project] - Applied environment variables from configuration"
     );
+    configure_uv_locations(&config.options, &project_root)?;
 
-    // Determine entrypoint
-    let run_mode: RunMode;
-    let entrypoint = &config.package.entrypoint;
-    let entry_point_path = project_dir.join(entrypoint);
-
-    // Check if the entrypoint path exists in the project directory
-    if !entry_point_path.exists() {
-        return Err(io::Error::new(
-            io::ErrorKind::NotFound,
-            format!("Entry point {} not found", entry_point_path.display()),
-        ));
-    }
-
+    let kind = entrypoint.kind().map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid entrypoint: {}", e),
+        )
+    })?;
     debug_println!(
-        "[main.run_extracted_project] - Using entry point: {}",
-        entrypoint
+        "[main.run_extracted_project] - Using entry point: {:?}",
+        kind
     );
 
-    // Determine run mode based on entrypoint extension
-    if entrypoint.ends_with(".py") {
-        run_mode = RunMode::Source;
-    } else if entrypoint.ends_with(".whl") {
-        run_mode = RunMode::Wheel;
-    } else {
-        run_mode = RunMode::App;
+    // Scripts and wheels are files of the payload, modules and console scripts are resolved by
+    // Python inside the environment
+    if let EntrypointKind::Script(path) | EntrypointKind::Wheel(path) = kind {
+        let entry_point_path = project_root.join(path);
+        if !entry_point_path.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Entry point {} not found", entry_point_path.display()),
+            ));
+        }
     }
 
+    let lock_flag = lock_flag(&project_root, &config);
     debug_println!(
-        "[main.run_extracted_project] - Determined run mode: {:#?}",
-        run_mode
+        "[main.run_extracted_project] - Lockfile flag: {:?}",
+        lock_flag
     );
 
-    // Grab the hooks from config and unwrap them to a tuple
-    let (pre_hook, post_hook) = prepare_hooks(&config);
-
-    // Run pre-hook
-    run_hook("pre-hook", &pre_hook, &uv_path, project_dir)?;
-
-    debug_println!("[main.run_extracted_project] - Running main project");
-    match run_mode {
-        RunMode::Source => {
-            debug_println!("[main.run_extracted_project] - Running in source mode");
-            let mut args_vec: Vec<String> = Vec::with_capacity(1 + runtime_args.len());
-            // Use entry_point_path rather than entry point to account for indirect project location reference
-            let project_entry_point: String;
-            match entry_point_path.to_str() {
-                None => {
-                    return Err(io::Error::new(
-                        io::ErrorKind::InvalidInput,
-                        "Could not extract entry point path",
-                    ));
-                }
-                Some(s) => project_entry_point = String::from(s),
-            }
-            args_vec.push(project_entry_point);
-            args_vec.extend(runtime_args);
-
-            let args_refs: Vec<&str> = args_vec.iter().map(|s| s.as_str()).collect();
-            run_uv(&uv_path, project_dir, &[], &args_refs)?;
+    let no_hooks = Hooks::default();
+    let hooks = config.hooks.as_ref().unwrap_or(&no_hooks);
+    let hook_context = HookContext {
+        uv_path: &uv_path,
+        project_root: &project_root,
+        lock_flag,
+        exit_code: None,
+        setup_message: None,
+    };
+
+    // Wheel builds install the embedded wheels into an ephemeral environment on every run
+    let wheel = config.wheel.as_ref().filter(|wheel| wheel.app.is_some());
+    let ephemeral = wheel.is_some() || matches!(kind, EntrypointKind::Wheel(_));
+
+    let with = match (kind, wheel) {
+        (EntrypointKind::Wheel(path), _) => {
+            vec![project_root.join(path).to_string_lossy().into_owned()]
         }
-        RunMode::Wheel => {
-            debug_println!("[main.run_extracted_project] - Running in wheel mode");
-            let wheel = find_single_wheel(project_dir)?;
-            let wheel_file = wheel.ok_or(io::Error::new(
-                io::ErrorKind::NotFound,
-                "No .whl file found in the project directory",
-            ))?;
-            run_uv(
-                &uv_path,
-                project_dir,
-                &[wheel_file.to_str().unwrap()],
-                &[config.package.entrypoint.as_str()],
-            )?;
+        (_, Some(wheel)) => wheel_requirements(project_dir, wheel),
+        _ => Vec::new(),
+    };
+    let script_path;
+    let code;
+    let mut args_vec: Vec<&str> = match kind {
+        EntrypointKind::Script(path) => {
+            // Joined to the project root to account for indirect project location reference
+            script_path = project_root.join(path).to_string_lossy().into_owned();
+            vec![&script_path]
+        }
+        EntrypointKind::Module(module) => vec!["python", "-m", module],
+        EntrypointKind::Callable { module, function } => {
+            code = callable_code(module, function);
+            vec!["python", "-c", &code]
         }
-        RunMode::App => {
-            debug_println!("[main.run_extracted_project] - Running in app mode");
-            run_uv(
-                &uv_path,
-                project_dir,
-                &[],
-                &[config.package.entrypoint.as_str()],
-            )?;
+        EntrypointKind::ConsoleScript(name) => vec![name],
+        // A console script named after the distribution, as wheel builds used to assume
+        EntrypointKind::Wheel(path) => {
+            let file_name = Path::new(path)
+                .file_name()
+                .map_or(path.into(), |name| name.to_string_lossy());
+            script_path = distribution_name(&file_name).replace('_', "-");
+            vec![&script_path]
         }
+    };
+    args_vec.extend(runtime_args.iter().map(String::as_str));
+    let with_refs: Vec<&str> = with.iter().map(String::as_str).collect();
+
+    let state_dir = app_dir()?;
+    let mut state = RunState::load(&state_dir);
+    let revision = Revision::current(project_dir)?;
+    if !ephemeral {
+        ensure_environment(&mut state, &revision, &config.options, hooks, &hook_context)?;
+        state.save(&state_dir)?;
     }
 
-    // Run post-hook
-    run_hook("post-hook", &post_hook, &uv_path, project_dir)?;
-
+    // A failing `on_first_run`, `on_update` or `pre_run` hook fails the run like the app would,
+    // `on_error`, `post_run` and the cleanup below still happen
+    let outcome = run_lifecycle_hooks(&mut state, &revision, hooks, &hook_context)
+        .and_then(|()| state.save(&state_dir))
+        .and_then(|()| run_hooks("pre_run", hooks.pre_run.as_ref(), &hook_context))
+        .and_then(|()| {
+            debug_println!(
+                "[main.run_extracted_project] - Running {:?} with {:?}",
+                args_vec,
+                with
+            );
+            if ephemeral {
+                run_uv(&uv_path, &project_root, None, &with_refs, &args_vec)
+            } else {
+                run_uv(&uv_path, &project_root, lock_flag, &[], &args_vec)
+            }
+        });
     // Clean up if delete_after_run is set or extract_to_temp is set
-    if (config.options.delete_after_run || config.options.extract_to_temp) && project_dir.exists() {
-        debug_println!("[main.run_extracted_project] - Cleaning up extracted project");
-        std::fs::remove_dir_all(project_dir)?;
+    let cleanup = config.options.delete_after_run || config.options.extract_to_temp;
+    finish_run(outcome, hooks, hook_context, cleanup.then_some(project_dir))
+}
+
+// Runs `on_error` and `post_run` for the outcome of the app, or of a hook that kept it from
+// running, and removes `cleanup_dir` either way
+fn finish_run(
+    outcome: io::Result<i32>,
+    hooks: &Hooks,
+    mut hook_context: HookContext,
+    cleanup_dir: Option<&Path>,
+) -> io::Result<i32> {
+    let (exit_code, failure) = match outcome {
+        Ok(exit_code) => (exit_code, None),
+        Err(e) => (1, Some(e)),
+    };
+    debug_println!("[main.finish_run] - Main project exited with {}", exit_code);
+
+    // `post_run` runs whether the app failed or not
+    hook_context.exit_code = Some(exit_code);
+    let on_error = if exit_code != 0 {
+        run_hooks("on_error", hooks.on_error.as_ref(), &hook_context)
+    } else {
+        Ok(())
+    };
+    let post_run = run_hooks("post_run", hooks.post_run.as_ref(), &hook_context);
+
+    if let Some(dir) = cleanup_dir.filter(|dir| dir.exists()) {
+        debug_println!("[main.finish_run] - Cleaning up extracted project");
+        std::fs::remove_dir_all(dir)?;
     }
 
-    Ok(())
+    for result in [on_error, post_run] {
+        match result {
+            // The exit code of a failed app matters more than a failed hook
+            Err(e) if exit_code != 0 => eprintln!("{}", e),
+            result => result?,
+        }
+    }
+    match failure {
+        Some(e) => Err(e),
+        None => Ok(exit_code),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use shared::config::{HookEntry, HookList, HookSpec};
+    use tempfile::tempdir;
+
+    fn shell(script: &str) -> HookList {
+        HookList::One(HookSpec::Entry(HookEntry {
+            command: Some("sh".to_string()),
+            args: vec!["-c".to_string(), script.to_string()],
+            ..Default::default()
+        }))
+    }
+
+    #[test]
+    fn test_failed_hook_runs_on_error_and_cleans_up() {
+        let dir = tempdir().unwrap();
+        let project_dir = dir.path().join("project");
+        std::fs::create_dir(&project_dir).unwrap();
+        let hooks = Hooks {
+            on_error: Some(shell("echo \"$PYCRUCIBLE_EXIT_CODE\" > on_error")),
+            post_run: Some(shell("touch post_run")),
+            ..Default::default()
+        };
+        let context = HookContext {
+            uv_path: Path::new("uv"),
+            project_root: dir.path(),
+            lock_flag: None,
+            exit_code: None,
+            setup_message: None,
+        };
+
+        let failed_hook = Err(io::Error::other("pre_run hook command `sh` failed"));
+        let err = finish_run(failed_hook, &hooks, context, Some(&project_dir)).unwrap_err();
+        assert!(err.to_string().contains("pre_run hook"));
+        let on_error = std::fs::read_to_string(dir.path().join("on_error")).unwrap();
+        assert_eq!(on_error.trim(), "1");
+        assert!(dir.path().join("post_run").exists());
+        assert!(!project_dir.exists());
+
+        std::fs::remove_file(dir.path().join("on_error")).unwrap();
+        assert_eq!(finish_run(Ok(0), &hooks, context, None).unwrap(), 0);
+        assert!(!dir.path().join("on_error").exists());
+    }
+
+    #[test]
+    fn test_wheel_requirements() {
+        let project_dir = Path::new("/tmp/my app");
+        let mut wheel = WheelConfig {
+            app: Some("my_app-1.0-py3-none-any.whl".to_string()),
+            wheels: vec!["private_dep-2.0-py3-none-any.whl".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            wheel_requirements(project_dir, &wheel),
+            [
+                "/tmp/my app/my_app-1.0-py3-none-any.whl",
+                "/tmp/my app/private_dep-2.0-py3-none-any.whl"
+            ]
+        );
+
+        wheel.extras = vec!["cli".to_string(), "yaml".to_string()];
+        assert_eq!(
+            wheel_requirements(project_dir, &wheel)[0],
+            "my_app[cli,yaml] @ file:///tmp/my%20app/my_app-1.0-py3-none-any.whl"
+        );
+    }
 }
@@ -0,0 +1,195 @@
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+use shared::config::{Hooks, ToolOptions, VenvLocation, VenvMode};
+use shared::debug_println;
+
+use crate::environment::resolve_runtime_path;
+use crate::hooks::{HookContext, run_hooks};
+use crate::run::uv_sync_command;
+use crate::state::{Revision, RunState};
+
+const DEFAULT_SETUP_MESSAGE: &str =
+    "Installing Python and dependencies, this only happens on the first run...";
+
+// Where uv keeps the project environment, `UV_PROJECT_ENVIRONMENT` may move it out of the project
+fn environment_dir(project_root: &Path) -> PathBuf {
+    match std::env::var_os("UV_PROJECT_ENVIRONMENT") {
+        Some(dir) => project_root.join(dir),
+        None => project_root.join(".venv"),
+    }
+}
+
+// `[project].name` from pyproject.toml, the name of the binary otherwise
+fn app_name(project_root: &Path) -> String {
+    let from_pyproject = std::fs::read_to_string(project_root.join("pyproject.toml"))
+        .ok()
+        .and_then(|raw| toml::from_str::<toml::Table>(&raw).ok())
+        .and_then(|doc| {
+            doc.get("project")?
+                .get("name")?
+                .as_str()
+                .map(str::to_string)
+        });
+    let name = from_pyproject
+        .or_else(|| {
+            std::env::current_exe()
+                .ok()?
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "app".to_string());
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// `<base>/<app name>-<hash>`, where the hash covers the lockfile (or the manifest without one)
+/// so a changed lockfile gets a fresh environment instead of mutating one still in use.
+fn persistent_venv_dir(base: &Path, project_root: &Path) -> PathBuf {
+    let manifest = ["uv.lock", "pyproject.toml", "requirements.txt"]
+        .iter()
+        .find_map(|name| std::fs::read(project_root.join(name)).ok())
+        .unwrap_or_default();
+    let digest: String = Sha256::digest(&manifest)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    base.join(format!("{}-{}", app_name(project_root), &digest[..12]))
+}
+
+/// Points uv at the configured environment and cache directories through
+/// `UV_PROJECT_ENVIRONMENT` and `UV_CACHE_DIR`, they then outlive the extracted project.
+pub fn configure_uv_locations(options: &ToolOptions, project_root: &Path) -> io::Result<()> {
+    let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidInput, e);
+    let venv_dir = match &options.venv {
+        VenvLocation::Mode(VenvMode::Project) => None,
+        VenvLocation::Mode(VenvMode::Persistent) => {
+            let data_dir = dirs::data_local_dir().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "Could not determine the user data directory",
+                )
+            })?;
+            Some(persistent_venv_dir(
+                &data_dir.join("pycrucible").join("venvs"),
+                project_root,
+            ))
+        }
+        VenvLocation::Path { path } => Some(resolve_runtime_path(path).map_err(invalid)?),
+    };
+    if let Some(venv_dir) = venv_dir {
+        debug_println!(
+            "[setup.configure_uv_locations] - UV_PROJECT_ENVIRONMENT={}",
+            venv_dir.display()
+        );
+        unsafe { std::env::set_var("UV_PROJECT_ENVIRONMENT", venv_dir) };
+    }
+    if let Some(cache_dir) = &options.uv_cache_dir {
+        let cache_dir = resolve_runtime_path(cache_dir).map_err(invalid)?;
+        debug_println!(
+            "[setup.configure_uv_locations] - UV_CACHE_DIR={}",
+            cache_dir.display()
+        );
+        unsafe { std::env::set_var("UV_CACHE_DIR", cache_dir) };
+    }
+    Ok(())
+}
+
+/// Syncs the project environment with an explicit `uv sync` unless it is already synced for the
+/// current revision and lockfile, so the first launch shows progress instead of stalling in
+/// `uv run`. Records the synced fingerprint in `state`.
+pub fn ensure_environment(
+    state: &mut RunState,
+    revision: &Revision,
+    options: &ToolOptions,
+    hooks: &Hooks,
+    context: &HookContext,
+) -> io::Result<()> {
+    let project_root = context.project_root;
+    if !project_root.join("pyproject.toml").exists() {
+        // Projects without a pyproject.toml are installed by `uv run` itself
+        debug_println!("[setup.ensure_environment] - No pyproject.toml, skipping setup");
+        return Ok(());
+    }
+
+    let lockfile = std::fs::read(project_root.join("uv.lock")).ok();
+    let fingerprint = revision.environment_fingerprint(lockfile.as_deref());
+    if state.environment.as_deref() == Some(fingerprint.as_str())
+        && environment_dir(project_root).exists()
+    {
+        debug_println!("[setup.ensure_environment] - Environment is up to date");
+        return Ok(());
+    }
+
+    let message = options
+        .setup_message
+        .as_deref()
+        .unwrap_or(DEFAULT_SETUP_MESSAGE);
+    let setup_context = HookContext {
+        setup_message: Some(message),
+        ..*context
+    };
+    run_hooks("on_setup", hooks.on_setup.as_ref(), &setup_context)?;
+
+    // A spinner would garble logs and pipes
+    let spinner = if std::io::stdout().is_terminal() {
+        Some(shared::spinner::create_spinner_with_message(message))
+    } else {
+        eprintln!("{}", message);
+        None
+    };
+    let status = uv_sync_command(context.uv_path, project_root, context.lock_flag).status();
+    let succeeded = matches!(&status, Ok(status) if status.success());
+    if let Some(mut spinner) = spinner {
+        if succeeded {
+            shared::spinner::stop_and_persist_spinner_with_message(spinner, "Environment ready");
+        } else {
+            spinner.stop_and_persist("✖", "Failed to set up the environment".into());
+        }
+    }
+    let status = status?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "Failed to set up the environment: uv sync exited with {}",
+            status
+        )));
+    }
+
+    state.environment = Some(fingerprint);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_persistent_venv_dir_is_keyed_by_name_and_lockfile() {
+        let project = tempdir().unwrap();
+        let base = Path::new("/data/venvs");
+        std::fs::write(
+            project.path().join("pyproject.toml"),
+            "[project]\nname = \"my app\"\n",
+        )
+        .unwrap();
+        std::fs::write(project.path().join("uv.lock"), "version = 1\n").unwrap();
+
+        let first = persistent_venv_dir(base, project.path());
+        assert!(first.starts_with(base));
+        let name = first.file_name().unwrap().to_string_lossy().into_owned();
+        assert!(name.starts_with("my_app-"));
+        assert_eq!(persistent_venv_dir(base, project.path()), first);
+
+        std::fs::write(project.path().join("uv.lock"), "version = 2\n").unwrap();
+        assert_ne!(persistent_venv_dir(base, project.path()), first);
+    }
+}
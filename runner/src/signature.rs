@@ -0,0 +1,305 @@
+use git2::{Oid, Repository};
+use shared::debug_println;
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use tempfile::tempdir;
+
+const SSH_SIGNATURE_MARKER: &str = "-----BEGIN SSH SIGNATURE-----";
+const PGP_SIGNATURE_MARKER: &str = "-----BEGIN PGP SIGNATURE-----";
+// Every key in the generated allowed signers file shares this principal,
+// we only care that *one* of the trusted keys produced the signature.
+const SSH_PRINCIPAL: &str = "pycrucible";
+
+#[derive(Debug, PartialEq)]
+enum TrustedSigner {
+    // Full public key line, e.g. `ssh-ed25519 AAAA... user@host`
+    Ssh(String),
+    // GPG fingerprint (40 or 64 hex digits) or long key id (16), uppercase without spaces
+    Gpg(String),
+}
+
+fn parse_signer(raw: &str) -> Result<TrustedSigner, String> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Err("empty trusted_signers entry".into());
+    }
+    if raw.starts_with("ssh-") || raw.starts_with("ecdsa-") || raw.starts_with("sk-") {
+        if raw.split_whitespace().count() < 2 {
+            return Err(format!("SSH key `{}` has no key data", raw));
+        }
+        return Ok(TrustedSigner::Ssh(raw.to_string()));
+    }
+    let normalized: String = raw.chars().filter(|c| !c.is_whitespace()).collect();
+    let fingerprint = normalized
+        .strip_prefix("0x")
+        .unwrap_or(&normalized)
+        .to_uppercase();
+    // Short (8 digit) key ids are easy to collide, so they are not accepted
+    if !fingerprint.chars().all(|c| c.is_ascii_hexdigit())
+        || ![16, 40, 64].contains(&fingerprint.len())
+    {
+        return Err(format!(
+            "`{}` is neither an SSH public key nor a full GPG fingerprint or 16 digit long key id",
+            raw
+        ));
+    }
+    Ok(TrustedSigner::Gpg(fingerprint))
+}
+
+// A v4 fingerprint ends with its long key id, a v5 fingerprint starts with it
+fn long_key_id(fingerprint: &str) -> &str {
+    match fingerprint.len() {
+        40 => &fingerprint[24..],
+        64 => &fingerprint[..16],
+        _ => fingerprint,
+    }
+}
+
+fn is_trusted_fingerprint(fingerprint: &str, trusted: &str) -> bool {
+    if trusted.len() == 16 {
+        long_key_id(fingerprint) == trusted
+    } else {
+        fingerprint == trusted
+    }
+}
+
+/// Splits a raw annotated tag object into (signed payload, signature).
+fn split_tag_signature(raw: &[u8]) -> Option<(Vec<u8>, String)> {
+    let content = String::from_utf8_lossy(raw);
+    let start = [SSH_SIGNATURE_MARKER, PGP_SIGNATURE_MARKER]
+        .iter()
+        .filter_map(|marker| content.find(marker))
+        .min()?;
+    Some((
+        content[..start].as_bytes().to_vec(),
+        content[start..].to_string(),
+    ))
+}
+
+/// Returns the fingerprints reported by `VALIDSIG` in gpg's `--status-fd` output.
+/// Both the signing (sub)key and the primary key fingerprint are returned.
+fn parse_gpg_validsig(status: &str) -> Vec<String> {
+    status
+        .lines()
+        .filter_map(|line| line.strip_prefix("[GNUPG:] VALIDSIG "))
+        .flat_map(|rest| {
+            let fields: Vec<&str> = rest.split_whitespace().collect();
+            let mut fprs = Vec::new();
+            if let Some(fpr) = fields.first() {
+                fprs.push(fpr.to_uppercase());
+            }
+            if let Some(primary) = fields.get(9) {
+                fprs.push(primary.to_uppercase());
+            }
+            fprs
+        })
+        .collect()
+}
+
+fn verify_ssh(signature: &str, payload: &[u8], keys: &[&String]) -> Result<(), String> {
+    if keys.is_empty() {
+        return Err("signed with an SSH key but no SSH keys are trusted".into());
+    }
+    let dir = tempdir().map_err(|e| e.to_string())?;
+    let allowed_signers = dir.path().join("allowed_signers");
+    let signature_file = dir.path().join("signature");
+    let allowed = keys
+        .iter()
+        .map(|k| format!("{} {}", SSH_PRINCIPAL, k))
+        .collect::<Vec<_>>()
+        .join("\n");
+    fs::write(&allowed_signers, allowed).map_err(|e| e.to_string())?;
+    fs::write(&signature_file, signature).map_err(|e| e.to_string())?;
+
+    let mut child = Command::new("ssh-keygen")
+        .args(["-Y", "verify", "-n", "git", "-I", SSH_PRINCIPAL, "-f"])
+        .arg(&allowed_signers)
+        .arg("-s")
+        .arg(&signature_file)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("could not run ssh-keygen: {}", e))?;
+    child
+        .stdin
+        .take()
+        .ok_or("could not open ssh-keygen stdin")?
+        .write_all(payload)
+        .map_err(|e| e.to_string())?;
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "SSH signature is not from a trusted key: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+// Verifies against the default keyring of the user running the binary, the trusted keys have to
+// be imported there; `trusted_signers` only says which of the keys found there are accepted
+fn verify_gpg(signature: &str, payload: &[u8], fingerprints: &[&String]) -> Result<(), String> {
+    if fingerprints.is_empty() {
+        return Err("signed with a GPG key but no GPG keys are trusted".into());
+    }
+    let dir = tempdir().map_err(|e| e.to_string())?;
+    let signature_file = dir.path().join("signature.asc");
+    fs::write(&signature_file, signature).map_err(|e| e.to_string())?;
+
+    let mut child = Command::new("gpg")
+        .args(["--batch", "--status-fd", "1", "--verify"])
+        .arg(&signature_file)
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("could not run gpg: {}", e))?;
+    child
+        .stdin
+        .take()
+        .ok_or("could not open gpg stdin")?
+        .write_all(payload)
+        .map_err(|e| e.to_string())?;
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    let valid = parse_gpg_validsig(&String::from_utf8_lossy(&output.stdout));
+    if valid.is_empty() {
+        return Err("GPG signature could not be verified".into());
+    }
+    if valid.iter().any(|fpr| {
+        fingerprints
+            .iter()
+            .any(|trusted| is_trusted_fingerprint(fpr, trusted))
+    }) {
+        Ok(())
+    } else {
+        Err(format!(
+            "GPG signature is from an untrusted key ({})",
+            valid.join(", ")
+        ))
+    }
+}
+
+fn verify_payload(signature: &str, payload: &[u8], trusted: &[String]) -> Result<(), String> {
+    let signers = trusted
+        .iter()
+        .map(|s| parse_signer(s))
+        .collect::<Result<Vec<_>, _>>()?;
+    if signature.starts_with(SSH_SIGNATURE_MARKER) {
+        let keys: Vec<&String> = signers
+            .iter()
+            .filter_map(|s| match s {
+                TrustedSigner::Ssh(k) => Some(k),
+                TrustedSigner::Gpg(_) => None,
+            })
+            .collect();
+        verify_ssh(signature, payload, &keys)
+    } else {
+        let fingerprints: Vec<&String> = signers
+            .iter()
+            .filter_map(|s| match s {
+                TrustedSigner::Gpg(f) => Some(f),
+                TrustedSigner::Ssh(_) => None,
+            })
+            .collect();
+        verify_gpg(signature, payload, &fingerprints)
+    }
+}
+
+/// Checks that the commit `oid` is signed by one of the `trusted` signers.
+pub fn verify_commit(repo: &Repository, oid: Oid, trusted: &[String]) -> Result<(), String> {
+    debug_println!(
+        "[signature.verify_commit] - Verifying signature of commit {}",
+        oid
+    );
+    let (signature, payload) = repo
+        .extract_signature(&oid, None)
+        .map_err(|_| format!("commit {} is not signed", oid))?;
+    let signature = signature
+        .as_str()
+        .ok_or_else(|| format!("commit {} has a non UTF-8 signature", oid))?;
+    verify_payload(signature, &payload, trusted).map_err(|e| format!("commit {}: {}", oid, e))
+}
+
+/// Checks that the annotated tag `oid` is signed by one of the `trusted` signers.
+pub fn verify_tag(repo: &Repository, oid: Oid, trusted: &[String]) -> Result<(), String> {
+    debug_println!(
+        "[signature.verify_tag] - Verifying signature of tag {}",
+        oid
+    );
+    let odb = repo.odb().map_err(|e| e.to_string())?;
+    let object = odb.read(oid).map_err(|e| e.to_string())?;
+    let (payload, signature) =
+        split_tag_signature(object.data()).ok_or_else(|| format!("tag {} is not signed", oid))?;
+    verify_payload(&signature, &payload, trusted).map_err(|e| format!("tag {}: {}", oid, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_signer() {
+        assert_eq!(
+            parse_signer("ssh-ed25519 AAAAC3Nza user@host"),
+            Ok(TrustedSigner::Ssh(
+                "ssh-ed25519 AAAAC3Nza user@host".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_signer("0123 4567 89ab CDEF 0123  4567 89AB CDEF 0123 4567"),
+            Ok(TrustedSigner::Gpg(
+                "0123456789ABCDEF0123456789ABCDEF01234567".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_signer("0x89abcdef01234567"),
+            Ok(TrustedSigner::Gpg("89ABCDEF01234567".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_signer_rejects_empty_and_short_ids() {
+        assert!(parse_signer("").is_err());
+        assert!(parse_signer("   ").is_err());
+        assert!(parse_signer("ssh-ed25519").is_err());
+        // 8 digit short key ids can be forged
+        assert!(parse_signer("0x01234567").is_err());
+        assert!(parse_signer("not-a-fingerprint-at-all").is_err());
+        assert!(verify_payload(PGP_SIGNATURE_MARKER, b"", &[" ".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_trusted_fingerprint_is_compared_for_equality() {
+        let fpr = "0123456789ABCDEF0123456789ABCDEF01234567";
+        assert!(is_trusted_fingerprint(fpr, fpr));
+        assert!(is_trusted_fingerprint(fpr, "89ABCDEF01234567"));
+        // A suffix that is not the long key id does not match
+        assert!(!is_trusted_fingerprint(fpr, "CDEF0123456789ABCDEF01234567"));
+        assert!(!is_trusted_fingerprint(
+            fpr,
+            "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"
+        ));
+        assert!(!is_trusted_fingerprint(fpr, ""));
+    }
+
+    #[test]
+    fn test_split_tag_signature() {
+        let raw = b"object abc\ntype commit\ntag v1\n\nmessage\n-----BEGIN SSH SIGNATURE-----\nxyz\n-----END SSH SIGNATURE-----\n";
+        let (payload, signature) = split_tag_signature(raw).unwrap();
+        assert_eq!(payload, b"object abc\ntype commit\ntag v1\n\nmessage\n");
+        assert!(signature.starts_with(SSH_SIGNATURE_MARKER));
+
+        assert!(split_tag_signature(b"object abc\n\nunsigned\n").is_none());
+    }
+
+    #[test]
+    fn test_parse_gpg_validsig() {
+        let status = "[GNUPG:] NEWSIG\n[GNUPG:] GOODSIG 1234 user\n[GNUPG:] VALIDSIG aaaa 2024-01-01 1704067200 0 4 0 22 10 00 bbbb\n";
+        assert_eq!(parse_gpg_validsig(status), vec!["AAAA", "BBBB"]);
+        assert!(parse_gpg_validsig("[GNUPG:] BADSIG 1234 user\n").is_empty());
+    }
+}
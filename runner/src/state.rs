@@ -0,0 +1,174 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use shared::debug_println;
+
+use crate::repository::SOURCE_CHECKOUT_DIR;
+
+const STATE_FILE: &str = "state.toml";
+
+/// Identifies what a run of the binary executes.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Revision {
+    // Payload offset, size and modification time of the binary
+    pub payload: String,
+    // Commit checked out from `[source]`, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_revision: Option<String>,
+}
+
+/// What the runner remembers between runs of an installed binary.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct RunState {
+    // Revision `on_first_run`/`on_update` last completed for, `None` until the first run did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<Revision>,
+    // Fingerprint of the revision and lockfile the environment was last synced for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+}
+
+fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Identifies the payload of `exe` without reading it: rebuilding or replacing the binary changes
+// its size or modification time, and the payload offset comes from the footer. This runs on
+// every launch, hashing a payload of hundreds of megabytes would delay each of them.
+fn payload_key(exe: &Path) -> io::Result<String> {
+    let info = shared::footer::read_footer_from(exe)?;
+    let metadata = fs::metadata(exe)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Ok(format!(
+        "{}-{}-{}",
+        info.offset,
+        metadata.len(),
+        modified.as_nanos()
+    ))
+}
+
+fn source_revision(project_dir: &Path) -> Option<String> {
+    let repo = git2::Repository::open(project_dir.join(SOURCE_CHECKOUT_DIR)).ok()?;
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    Some(commit.id().to_string())
+}
+
+/// `~/.pycrucible/apps/<binary name>-<hash of its path>`, it survives `extract_to_temp` and
+/// `delete_after_run` and copies of the binary in different places do not share it.
+pub fn app_dir() -> io::Result<PathBuf> {
+    let exe = std::env::current_exe()?;
+    let name = exe
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "app".to_string());
+    let path_digest = hex(&Sha256::digest(exe.to_string_lossy().as_bytes()));
+    let home = dirs::home_dir().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "Could not determine home directory",
+        )
+    })?;
+    Ok(home
+        .join(".pycrucible")
+        .join("apps")
+        .join(format!("{}-{}", name, &path_digest[..12])))
+}
+
+impl Revision {
+    /// The revision of the binary that is running now.
+    pub fn current(project_dir: &Path) -> io::Result<Self> {
+        Ok(Revision {
+            payload: payload_key(&std::env::current_exe()?)?,
+            source_revision: source_revision(project_dir),
+        })
+    }
+
+    /// Changes whenever the revision or the lockfile (`None` without one) changes.
+    pub fn environment_fingerprint(&self, lockfile: Option<&[u8]>) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.payload.as_bytes());
+        for part in [self.source_revision.as_deref().map(str::as_bytes), lockfile] {
+            hasher.update(b"\n");
+            hasher.update(part.unwrap_or_default());
+        }
+        hex(&hasher.finalize())
+    }
+}
+
+impl RunState {
+    /// The state saved by previous runs, empty if the binary never ran (or the file is unreadable).
+    pub fn load(dir: &Path) -> Self {
+        let Ok(content) = fs::read_to_string(dir.join(STATE_FILE)) else {
+            return RunState::default();
+        };
+        toml::from_str(&content).unwrap_or_else(|e| {
+            debug_println!("[state.load] - Ignoring invalid state file: {}", e);
+            RunState::default()
+        })
+    }
+
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let content = toml::to_string(self).map_err(io::Error::other)?;
+        fs::write(dir.join(STATE_FILE), content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_run_state_round_trip() {
+        let dir = tempdir().unwrap();
+        assert!(RunState::load(dir.path()).revision.is_none());
+
+        let revision = Revision {
+            payload: "abc".to_string(),
+            source_revision: None,
+        };
+        let state = RunState {
+            environment: Some(revision.environment_fingerprint(Some(b"lock"))),
+            revision: Some(revision.clone()),
+        };
+        state.save(dir.path()).unwrap();
+
+        let loaded = RunState::load(dir.path());
+        assert_eq!(loaded.revision, Some(revision.clone()));
+        assert_eq!(loaded.environment, state.environment);
+        assert_ne!(
+            loaded.environment.unwrap(),
+            revision.environment_fingerprint(Some(b"changed lock"))
+        );
+
+        fs::write(dir.path().join(STATE_FILE), "not = [valid").unwrap();
+        assert!(RunState::load(dir.path()).environment.is_none());
+    }
+
+    #[test]
+    fn test_payload_key_changes_with_the_binary() {
+        let dir = tempdir().unwrap();
+        let exe = dir.path().join("app");
+        let write_binary = |payload: &[u8]| {
+            let mut binary = b"runner".to_vec();
+            binary.extend_from_slice(payload);
+            binary.extend(shared::footer::create_footer(false, 6));
+            fs::write(&exe, binary).unwrap();
+        };
+
+        write_binary(b"payload");
+        let key = payload_key(&exe).unwrap();
+        assert_eq!(payload_key(&exe).unwrap(), key);
+
+        write_binary(b"a rebuilt payload");
+        assert_ne!(payload_key(&exe).unwrap(), key);
+    }
+}
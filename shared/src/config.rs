@@ -1,13 +1,16 @@
 #![cfg_attr(test, allow(dead_code, unused_variables, unused_imports))]
 
+use schemars::JsonSchema;
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 
 use crate::debug_println;
-use std::path::{Path, PathBuf};
+use crate::uv_handler::DEFAULT_UV_VERSION;
+use std::path::Path;
 
-#[derive(serde::Serialize, Debug, Deserialize)]
+#[derive(serde::Serialize, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct FilePatterns {
     #[serde(default)]
     pub include: Vec<String>,
@@ -18,60 +21,413 @@ pub struct FilePatterns {
 impl Default for FilePatterns {
     fn default() -> Self {
         FilePatterns {
-            include: vec!["**/*.py".to_string()],
+            // Everything not ignored by `.gitignore` or the builder's built-in ignores (`.env*`,
+            // virtual environments, build output), so data files and extension modules ship too
+            include: vec!["**/*".to_string()],
             exclude: vec![
                 ".venv/**/*".to_string(),
                 "**/__pycache__/**".to_string(),
                 ".git/**/*".to_string(),
                 "**/*.pyc".to_string(),
                 "**/*.pyo".to_string(),
-                "**/*.pyd".to_string(),
             ],
         }
     }
 }
 
+/// What the app runs: a path or name whose kind is guessed from it (`.py` script, `.whl` wheel,
+/// console script otherwise), or a table naming the kind explicitly.
+#[derive(serde::Serialize, Debug, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum EntrypointSpec {
+    Path(String),
+    Entry(EntrypointEntry),
+}
+
+#[derive(serde::Serialize, Debug, Deserialize, Clone, PartialEq, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct EntrypointEntry {
+    // Python file run with `uv run`, relative to the project root
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+    // Module run with `python -m`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub module: Option<String>,
+    // `module:function` called without arguments, its return value is the exit code
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub callable: Option<String>,
+    // A script from `[project.scripts]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub console_script: Option<String>,
+}
+
+/// How an entrypoint is run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntrypointKind<'a> {
+    Script(&'a str),
+    Module(&'a str),
+    Callable { module: &'a str, function: &'a str },
+    ConsoleScript(&'a str),
+    Wheel(&'a str),
+}
+
+// `a.b_c`, safe to place into generated Python code
+fn is_dotted_identifier(name: &str) -> bool {
+    name.split('.').all(|part| {
+        part.chars()
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || c == '_')
+            && part.chars().all(|c| c.is_alphanumeric() || c == '_')
+    })
+}
+
+impl EntrypointSpec {
+    /// An error if an entrypoint table sets none or several kinds, or a module name is invalid.
+    pub fn kind(&self) -> Result<EntrypointKind<'_>, String> {
+        let entry = match self {
+            EntrypointSpec::Path(path) if path.ends_with(".py") => {
+                return Ok(EntrypointKind::Script(path));
+            }
+            EntrypointSpec::Path(path) if path.ends_with(".whl") => {
+                return Ok(EntrypointKind::Wheel(path));
+            }
+            EntrypointSpec::Path(name) => return Ok(EntrypointKind::ConsoleScript(name)),
+            EntrypointSpec::Entry(entry) => entry,
+        };
+        let kinds = [
+            &entry.script,
+            &entry.module,
+            &entry.callable,
+            &entry.console_script,
+        ];
+        if kinds.iter().filter(|kind| kind.is_some()).count() != 1 {
+            return Err(
+                "an entrypoint needs exactly one of `script`, `module`, `callable` or `console_script`"
+                    .to_string(),
+            );
+        }
+        if let Some(script) = &entry.script {
+            Ok(EntrypointKind::Script(script))
+        } else if let Some(module) = &entry.module {
+            if !is_dotted_identifier(module) {
+                return Err(format!("`{}` is not a valid module name", module));
+            }
+            Ok(EntrypointKind::Module(module))
+        } else if let Some(callable) = &entry.callable {
+            match callable.split_once(':') {
+                Some((module, function))
+                    if is_dotted_identifier(module) && is_dotted_identifier(function) =>
+                {
+                    Ok(EntrypointKind::Callable { module, function })
+                }
+                _ => Err(format!(
+                    "callable `{}` must look like `package.module:function`",
+                    callable
+                )),
+            }
+        } else {
+            Ok(EntrypointKind::ConsoleScript(
+                entry.console_script.as_deref().unwrap_or_default(),
+            ))
+        }
+    }
+
+    /// `entrypoint = ""`, only allowed together with named entrypoints.
+    pub fn is_empty(&self) -> bool {
+        matches!(self, EntrypointSpec::Path(path) if path.is_empty())
+    }
+
+    /// The plain string form, which may name one of the named entrypoints.
+    pub fn as_name(&self) -> Option<&str> {
+        match self {
+            EntrypointSpec::Path(name) => Some(name),
+            EntrypointSpec::Entry(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for EntrypointSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind() {
+            Ok(EntrypointKind::Module(module)) => write!(f, "-m {}", module),
+            Ok(EntrypointKind::Callable { module, function }) => {
+                write!(f, "{}:{}", module, function)
+            }
+            Ok(
+                EntrypointKind::Script(name)
+                | EntrypointKind::ConsoleScript(name)
+                | EntrypointKind::Wheel(name),
+            ) => write!(f, "{}", name),
+            Err(_) => write!(f, "<invalid entrypoint>"),
+        }
+    }
+}
+
+impl From<&str> for EntrypointSpec {
+    fn from(path: &str) -> Self {
+        EntrypointSpec::Path(path.to_string())
+    }
+}
+
+impl From<String> for EntrypointSpec {
+    fn from(path: String) -> Self {
+        EntrypointSpec::Path(path)
+    }
+}
+
+impl PartialEq<&str> for EntrypointSpec {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_name() == Some(*other)
+    }
+}
+
 #[derive(serde::Serialize, Debug, Deserialize)]
 pub struct PackageConfig {
+    // The default command, empty when only named `entrypoints` are configured
     #[serde(alias = "entry")]
-    pub entrypoint: String,
+    pub entrypoint: EntrypointSpec,
+    // Named commands, selected by the name the binary is invoked as or by the first argument
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub entrypoints: BTreeMap<String, EntrypointSpec>,
     #[serde(default)]
     pub patterns: FilePatterns,
+    // Globs embedded even when `.gitignore` or `patterns.exclude` would leave them out
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resources: Vec<String>,
+}
+
+impl PackageConfig {
+    /// The default command with named entrypoints resolved, `None` if there is none.
+    pub fn default_entrypoint(&self) -> Option<&EntrypointSpec> {
+        if self.entrypoint.is_empty() {
+            return None;
+        }
+        let named = self
+            .entrypoint
+            .as_name()
+            .and_then(|name| self.entrypoints.get(name));
+        Some(named.unwrap_or(&self.entrypoint))
+    }
 }
 
 impl Default for PackageConfig {
     fn default() -> Self {
         PackageConfig {
             entrypoint: "main.py".into(),
+            entrypoints: BTreeMap::new(),
             patterns: FilePatterns::default(),
+            resources: Vec::new(),
         }
     }
 }
 
-#[derive(serde::Serialize, Debug, Deserialize, Default)]
+#[derive(serde::Serialize, Debug, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct UVConfig {
     pub args: Option<Vec<String>>,
 }
 
-#[derive(serde::Serialize, Debug, Deserialize, Default)]
+/// An `[env]` entry: a plain (interpolated) value or a table for list-style and unset changes.
+#[derive(serde::Serialize, Debug, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum EnvValue {
+    Value(String),
+    Entry(EnvEntry),
+}
+
+#[derive(serde::Serialize, Debug, Deserialize, Clone, PartialEq, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct EnvEntry {
+    // Replaces the inherited value before `prepend`/`append` are applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prepend: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub append: Vec<String>,
+    // Defaults to the platform's path list separator (`:` or `;`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub separator: Option<String>,
+    // Remove the variable from the environment of the app
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unset: bool,
+}
+
+#[derive(serde::Serialize, Debug, Deserialize, Default, JsonSchema)]
 pub struct EnvConfig {
     #[serde(flatten)]
-    pub variables: Option<HashMap<String, String>>,
+    pub variables: Option<HashMap<String, EnvValue>>,
+}
+
+/// A hook: the path of a Python script, or a table for modules, commands and per-hook settings.
+#[derive(serde::Serialize, Debug, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum HookSpec {
+    Script(String),
+    Entry(HookEntry),
+}
+
+#[derive(serde::Serialize, Debug, Deserialize, Clone, PartialEq, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct HookEntry {
+    // Python script run with `uv run`, relative to the project root
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+    // Python module run with `uv run python -m`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub module: Option<String>,
+    // Executable run directly in the project root, without uv
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    // Seconds after which the hook is killed and counts as failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    // Report a failure but carry on with the next hook and the app
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub continue_on_error: bool,
+}
+
+/// What a hook runs.
+#[derive(Debug, PartialEq)]
+pub enum HookKind<'a> {
+    Script(&'a str),
+    Module(&'a str),
+    Command(&'a str),
+}
+
+impl HookSpec {
+    /// An error if a hook table sets none or more than one of `script`, `module` and `command`.
+    pub fn kind(&self) -> Result<HookKind<'_>, String> {
+        let entry = match self {
+            HookSpec::Script(script) => return Ok(HookKind::Script(script)),
+            HookSpec::Entry(entry) => entry,
+        };
+        match (&entry.script, &entry.module, &entry.command) {
+            (Some(script), None, None) => Ok(HookKind::Script(script)),
+            (None, Some(module), None) => Ok(HookKind::Module(module)),
+            (None, None, Some(command)) => Ok(HookKind::Command(command)),
+            _ => Err("a hook needs exactly one of `script`, `module` or `command`".to_string()),
+        }
+    }
+
+    pub fn args(&self) -> &[String] {
+        match self {
+            HookSpec::Script(_) => &[],
+            HookSpec::Entry(entry) => &entry.args,
+        }
+    }
+
+    pub fn timeout(&self) -> Option<u64> {
+        match self {
+            HookSpec::Script(_) => None,
+            HookSpec::Entry(entry) => entry.timeout,
+        }
+    }
+
+    pub fn continue_on_error(&self) -> bool {
+        match self {
+            HookSpec::Script(_) => false,
+            HookSpec::Entry(entry) => entry.continue_on_error,
+        }
+    }
+}
+
+/// A single hook or a list of hooks run in order.
+#[derive(serde::Serialize, Debug, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum HookList {
+    One(HookSpec),
+    Many(Vec<HookSpec>),
 }
 
-#[derive(serde::Serialize, Debug, Deserialize, Default)]
+impl HookList {
+    pub fn hooks(&self) -> &[HookSpec] {
+        match self {
+            HookList::One(hook) => std::slice::from_ref(hook),
+            HookList::Many(hooks) => hooks,
+        }
+    }
+}
+
+#[derive(serde::Serialize, Debug, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Hooks {
-    pub pre_run: Option<String>,
-    pub post_run: Option<String>,
+    // Before the app, a failure stops the run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_run: Option<HookList>,
+    // After the app, also when it failed, with its exit code in `PYCRUCIBLE_EXIT_CODE`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_run: Option<HookList>,
+    // When the app exits with a non-zero code, before `post_run`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_error: Option<HookList>,
+    // Before the environment is set up, with the message in `PYCRUCIBLE_SETUP_MESSAGE`,
+    // e.g. to show it in a GUI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_setup: Option<HookList>,
+    // Once per installation, before `pre_run`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_first_run: Option<HookList>,
+    // When the payload or the `[source]` checkout changed since the last run, before `pre_run`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_update: Option<HookList>,
 }
 
-#[derive(serde::Serialize, Debug, Deserialize, Clone)]
+impl Hooks {
+    /// Every configured hook with the name of its hook point.
+    pub fn all(&self) -> impl Iterator<Item = (&'static str, &HookSpec)> {
+        [
+            ("on_setup", &self.on_setup),
+            ("on_first_run", &self.on_first_run),
+            ("on_update", &self.on_update),
+            ("pre_run", &self.pre_run),
+            ("on_error", &self.on_error),
+            ("post_run", &self.post_run),
+        ]
+        .into_iter()
+        .filter_map(|(point, list)| list.as_ref().map(|list| (point, list)))
+        .flat_map(|(point, list)| list.hooks().iter().map(move |hook| (point, hook)))
+    }
+}
+
+/// `[wheel]`, used when building from a `.whl` file.
+#[derive(serde::Serialize, Debug, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct WheelConfig {
+    // Extras of the app wheel to install, e.g. `["cli"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extras: Vec<String>,
+    // More wheels to embed and install next to the app, e.g. private dependencies. Relative
+    // paths are resolved against the directory of the app wheel, embedded ones are file names.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wheels: Vec<String>,
+    // File name of the embedded app wheel, set by the builder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app: Option<String>,
+}
+
+#[derive(serde::Serialize, Debug, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct SourceConfig {
+    // Required, but may come from a different layer than the rest of `[source]`
+    #[serde(default)]
     pub repository: String,
     pub branch: Option<String>,
     pub tag: Option<String>,
     pub commit: Option<String>,
     pub update_strategy: Option<String>, // "pull" or "fetch"
+    // SSH public keys, full GPG fingerprints or 16 digit long key ids allowed to sign commits and tags
+    pub trusted_signers: Option<Vec<String>>,
+    // Project root within the repository, used as `--project` for uv
+    pub subdirectory: Option<String>,
+    // Recursively initialize and update submodules
+    pub submodules: Option<bool>,
+    // Shallow clone depth, full history when not set
+    pub depth: Option<i32>,
+    // Only check out these paths (the subdirectory is always included)
+    pub sparse_paths: Option<Vec<String>>,
 }
 impl Default for SourceConfig {
     fn default() -> Self {
@@ -81,11 +437,115 @@ impl Default for SourceConfig {
             tag: None,
             commit: None,
             update_strategy: Some("pull".to_string()), // Default to "pull"
+            trusted_signers: None,
+            subdirectory: None,
+            submodules: None,
+            depth: None,
+            sparse_paths: None,
+        }
+    }
+}
+
+// A relative path that stays below the directory it is joined to
+fn stays_inside(path: &str) -> bool {
+    !path.starts_with(['/', '\\'])
+        && !Path::new(path).has_root()
+        && Path::new(path).components().all(|part| {
+            matches!(
+                part,
+                std::path::Component::Normal(_) | std::path::Component::CurDir
+            )
+        })
+        && !path.split(['/', '\\']).any(|part| part == "..")
+}
+
+impl SourceConfig {
+    /// Checks that `subdirectory` and `sparse_paths` cannot leave the checkout.
+    pub fn validate(&self) -> Result<(), String> {
+        let paths = self
+            .subdirectory
+            .iter()
+            .map(|path| ("source.subdirectory", path))
+            .chain(
+                self.sparse_paths
+                    .iter()
+                    .flatten()
+                    .map(|path| ("source.sparse_paths", path)),
+            );
+        for (key, path) in paths {
+            if !stays_inside(path) {
+                return Err(format!(
+                    "Invalid configuration: `{}` must be a relative path inside the repository, got `{}`",
+                    key, path
+                ));
+            }
         }
+        Ok(())
     }
 }
 
-#[derive(serde::Serialize, Debug, Deserialize, Clone, Default)]
+/// Whether a uv found outside of the PyCrucible cache (on `PATH` or next to the binary) may be used.
+#[derive(
+    serde::Serialize,
+    Debug,
+    Deserialize,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    clap::ValueEnum,
+    JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum SystemUvPolicy {
+    // Only if its version matches `uv_version`
+    #[default]
+    Compatible,
+    // Always use the cached (or downloaded) uv
+    Never,
+    // Use it regardless of its version
+    Any,
+}
+
+/// How the runner holds uv to the embedded `uv.lock`.
+#[derive(serde::Serialize, Debug, Deserialize, Clone, Copy, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LockMode {
+    // `--locked`: fail if the lockfile no longer matches the project
+    #[default]
+    Locked,
+    // `--frozen`: install from the lockfile without checking it
+    Frozen,
+    // Do not embed or enforce a lockfile, dependencies are resolved on first run
+    Off,
+}
+
+/// Where the runner keeps the project's virtual environment.
+#[derive(serde::Serialize, Debug, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum VenvLocation {
+    Mode(VenvMode),
+    // `${VAR}` may be used, relative paths are resolved against the directory of the binary
+    Path { path: String },
+}
+
+impl Default for VenvLocation {
+    fn default() -> Self {
+        VenvLocation::Mode(VenvMode::Project)
+    }
+}
+
+#[derive(serde::Serialize, Debug, Deserialize, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum VenvMode {
+    // `.venv` in the extracted project, thrown away with `extract_to_temp`/`delete_after_run`
+    Project,
+    // A directory per app and lockfile in the user data directory, kept across runs
+    Persistent,
+}
+
+#[derive(serde::Serialize, Debug, Deserialize, Clone, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct ToolOptions {
     #[serde(default)]
     pub debug: bool,
@@ -97,9 +557,103 @@ pub struct ToolOptions {
     pub offline_mode: bool,
     #[serde(default)]
     pub uv_version: String,
+    #[serde(default)]
+    pub system_uv: SystemUvPolicy,
+    #[serde(default)]
+    pub lock_mode: LockMode,
+    // `.env` files the runner applies before launching, relative paths are resolved against the
+    // directory of the binary and `${PYCRUCIBLE_*}` variables may be used
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env_files: Vec<String>,
+    // Do not pass the parent environment through, except the variables needed to run uv and Python
+    #[serde(default)]
+    pub clear_env: bool,
+    // Passed to uv as `UV_PROJECT_ENVIRONMENT`: "project", "persistent" or `{ path = "..." }`
+    #[serde(default)]
+    pub venv: VenvLocation,
+    // Passed to uv as `UV_CACHE_DIR`, uv's global cache when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uv_cache_dir: Option<String>,
+    // Shown while the runner installs Python and the dependencies on the first run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub setup_message: Option<String>,
+    // Explicit proxy for uv downloads, `HTTP(S)_PROXY`/`NO_PROXY` are honored otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    // PEM bundle with additional CA certificates for uv downloads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<String>,
+    // Base URL or `{version}`/`{target}`/`{ext}` template for uv release downloads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uv_mirror: Option<String>,
+    // Pinned SHA-256 of the uv release archive per target triple,
+    // the published `.sha256` file is used for targets not listed here
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uv_sha256: Option<BTreeMap<String, String>>,
+}
+
+/// A `[platform.<name>]` table, applied by the runner on matching hosts only.
+#[derive(serde::Serialize, Debug, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PlatformConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uv: Option<UVConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<EnvConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<Hooks>,
+}
+
+/// Platform specific overrides. `unix` applies on Linux and macOS, before the more specific section.
+#[derive(serde::Serialize, Debug, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PlatformSections {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unix: Option<PlatformConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linux: Option<PlatformConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub macos: Option<PlatformConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub windows: Option<PlatformConfig>,
+}
+
+impl PlatformSections {
+    pub fn is_empty(&self) -> bool {
+        self.sections().next().is_none()
+    }
+
+    /// The configured sections with their names, in the order they are applied.
+    pub fn sections(&self) -> impl Iterator<Item = (&'static str, &PlatformConfig)> {
+        [
+            ("unix", &self.unix),
+            ("linux", &self.linux),
+            ("macos", &self.macos),
+            ("windows", &self.windows),
+        ]
+        .into_iter()
+        .filter_map(|(name, section)| section.as_ref().map(|section| (name, section)))
+    }
+}
+
+/// The `[platform.<name>]` sections that apply to the host the runner is running on.
+pub fn host_platforms() -> Vec<&'static str> {
+    let mut platforms = Vec::new();
+    if cfg!(unix) {
+        platforms.push("unix");
+    }
+    if cfg!(target_os = "linux") {
+        platforms.push("linux");
+    } else if cfg!(target_os = "macos") {
+        platforms.push("macos");
+    } else if cfg!(windows) {
+        platforms.push("windows");
+    }
+    platforms
 }
 
 #[derive(serde::Serialize, Debug, Deserialize)]
+#[serde(from = "ProjectConfigFile")]
 pub struct ProjectConfig {
     #[serde(flatten)]
     pub package: PackageConfig,
@@ -113,28 +667,200 @@ pub struct ProjectConfig {
     pub env: Option<EnvConfig>,
     #[serde(default)]
     pub hooks: Option<Hooks>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wheel: Option<WheelConfig>,
+    // Name of the profile the binary was built with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    // Kept unresolved in the embedded config, see `ProjectConfig::for_platforms`
+    #[serde(default, skip_serializing_if = "PlatformSections::is_empty")]
+    pub platform: PlatformSections,
+}
+
+/// `pycrucible.toml` (or `[tool.pycrucible]`) as written by the user.
+/// `deny_unknown_fields` does not work together with `flatten`, so `ProjectConfig` is read
+/// through this flat mirror to reject unknown keys.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[schemars(title = "PyCrucible configuration")]
+pub struct ProjectConfigFile {
+    /// The main file (or command) your application runs, `main.py` unless set in any layer.
+    /// With `entrypoints` it is the default command and may name one of them.
+    #[serde(alias = "entry", default)]
+    pub entrypoint: Option<EntrypointSpec>,
+    /// Named commands, e.g. `serve = "src/serve.py"`, picked by the name the binary is invoked
+    /// as (through a link) or by its first argument
+    #[serde(default)]
+    pub entrypoints: BTreeMap<String, EntrypointSpec>,
+    #[serde(default)]
+    pub patterns: FilePatterns,
+    /// Data files embedded even when `.gitignore` or `patterns.exclude` leave them out,
+    /// e.g. `["myapp/templates/**", "myapp/py.typed"]`
+    #[serde(default)]
+    pub resources: Vec<String>,
+    #[serde(default)]
+    pub options: ToolOptions,
+    #[serde(default)]
+    pub source: Option<SourceConfig>,
+    #[serde(default)]
+    pub uv: Option<UVConfig>,
+    #[serde(default)]
+    pub env: Option<EnvConfig>,
+    #[serde(default)]
+    pub hooks: Option<Hooks>,
+    /// Extras and additional wheels for builds from a `.whl` file
+    #[serde(default)]
+    pub wheel: Option<WheelConfig>,
+    /// Named overrides selected with `--profile`, e.g. `[profiles.prod]`
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileConfig>,
+    /// Set by the builder to the profile the binary was built with
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Overrides for `unix`, `linux`, `macos` or `windows`, e.g. `[platform.windows.env]`
+    #[serde(default)]
+    pub platform: PlatformSections,
+}
+
+/// A `[profiles.<name>]` table, it may override any section of the configuration.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileConfig {
+    #[serde(alias = "entry", default)]
+    pub entrypoint: Option<EntrypointSpec>,
+    pub entrypoints: Option<BTreeMap<String, EntrypointSpec>>,
+    pub patterns: Option<FilePatterns>,
+    pub resources: Option<Vec<String>>,
+    pub options: Option<ToolOptions>,
+    pub source: Option<SourceConfig>,
+    pub uv: Option<UVConfig>,
+    pub env: Option<EnvConfig>,
+    pub hooks: Option<Hooks>,
+    pub wheel: Option<WheelConfig>,
+    pub platform: Option<PlatformSections>,
+}
+
+impl From<ProjectConfigFile> for ProjectConfig {
+    fn from(file: ProjectConfigFile) -> Self {
+        ProjectConfig {
+            package: PackageConfig {
+                // Named entrypoints alone do not need a default command
+                entrypoint: file.entrypoint.unwrap_or_else(|| {
+                    if file.entrypoints.is_empty() {
+                        "main.py".into()
+                    } else {
+                        "".into()
+                    }
+                }),
+                entrypoints: file.entrypoints,
+                patterns: file.patterns,
+                resources: file.resources,
+            },
+            options: file.options,
+            source: file.source,
+            uv: file.uv,
+            env: file.env,
+            hooks: file.hooks,
+            wheel: file.wheel,
+            profile: file.profile,
+            platform: file.platform,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PyProject {
+    tool: Option<PyProjectTool>,
+}
+
+#[derive(Deserialize)]
+struct PyProjectTool {
+    pycrucible: Option<ProjectConfigFile>,
+}
+
+// Keys serde lists in "unknown field `x`, expected one of `a`, `b`" errors
+fn suggest_key(message: &str) -> Option<String> {
+    let rest = message.split("unknown field `").nth(1)?;
+    let (unknown, expected) = rest.split_once('`')?;
+    expected
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(|candidate| (strsim::jaro_winkler(unknown, candidate), candidate))
+        .filter(|(score, _)| *score > 0.8)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+fn describe_error(path: &Path, error: toml::de::Error) -> String {
+    let message = error.to_string();
+    let mut description = format!(
+        "Invalid configuration in {}\n{}",
+        path.display(),
+        message.trim_end()
+    );
+    if let Some(suggestion) = suggest_key(error.message()) {
+        description.push_str(&format!("\nhelp: did you mean `{}`?", suggestion));
+    }
+    description
 }
 
 impl ProjectConfig {
     /// Load configuration from the specified file path.
     pub fn from_file(path: &Path) -> Result<Self, String> {
         let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
-        toml::from_str(&content).map_err(|e| e.to_string())
+        toml::from_str(&content).map_err(|e| describe_error(path, e))
     }
 
-    fn from_pyproject(path: &Path) -> Result<Self, String> {
+    /// Load `[tool.pycrucible]` from a pyproject.toml, `None` if there is no such section.
+    pub fn from_pyproject(path: &Path) -> Result<Option<Self>, String> {
         let raw = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
-        let doc: toml::Value = toml::from_str(&raw).map_err(|e| e.to_string())?;
+        // Deserialize the whole document so error locations point into pyproject.toml
+        let pyproject: PyProject = toml::from_str(&raw).map_err(|e| describe_error(path, e))?;
+        Ok(pyproject
+            .tool
+            .and_then(|tool| tool.pycrucible)
+            .map(ProjectConfig::from))
+    }
 
-        let tbl = doc
-            .get("tool")
-            .and_then(|t| t.get("pycrucible"))
-            .ok_or("no [tool.pycrucible] section")?;
-        // Re-serialize just that sub-table so we can leverage
-        // the existing `ProjectConfig` derive.
-        let slice = toml::to_string(tbl).map_err(|e| e.to_string())?;
+    /// Applies the `[platform.<name>]` sections for `platforms`, in order, on top of the rest of
+    /// the configuration. The builder embeds them unresolved, the runner resolves them at startup.
+    pub fn for_platforms(self, platforms: &[&str]) -> Result<Self, String> {
+        if self.platform.is_empty() {
+            return Ok(self);
+        }
+        let mut table = toml::Table::try_from(&self).map_err(|e| e.to_string())?;
+        let sections = table.remove("platform");
+        for name in platforms {
+            let Some(overlay) = sections
+                .as_ref()
+                .and_then(|sections| sections.get(*name))
+                .and_then(|overlay| overlay.as_table())
+            else {
+                continue;
+            };
+            debug_println!("[config] applying platform {}", name);
+            merge_layer(
+                &mut table,
+                overlay.clone(),
+                "",
+                &format!("platform {}", name),
+                &mut BTreeMap::new(),
+            );
+        }
+        table
+            .try_into()
+            .map_err(|e: toml::de::Error| format!("Invalid configuration: {}", e.message()))
+    }
+
+    /// `for_platforms` with the sections matching the current host.
+    pub fn for_host(self) -> Result<Self, String> {
+        self.for_platforms(&host_platforms())
+    }
 
-        toml::from_str(&slice).map_err(|e| e.to_string())
+    /// JSON Schema for `pycrucible.toml`, usable for editor autocompletion.
+    pub fn json_schema() -> schemars::Schema {
+        schemars::schema_for!(ProjectConfigFile)
     }
 }
 
@@ -143,70 +869,268 @@ impl Default for ProjectConfig {
         ProjectConfig {
             package: PackageConfig {
                 entrypoint: "main.py".into(),
-                patterns: FilePatterns {
-                    include: vec!["**/*.py".to_string()],
-                    exclude: vec![
-                        ".venv/**/*".to_string(),
-                        "**/__pycache__/**".to_string(),
-                        ".git/**/*".to_string(),
-                        "**/*.pyc".to_string(),
-                        "**/*.pyo".to_string(),
-                        "**/*.pyd".to_string(),
-                    ],
-                },
+                entrypoints: BTreeMap::new(),
+                patterns: FilePatterns::default(),
+                resources: Vec::new(),
+            },
+            options: ToolOptions {
+                uv_version: DEFAULT_UV_VERSION.to_string(),
+                ..Default::default()
             },
-            options: ToolOptions::default(),
             source: None,
             uv: None,
             env: None,
             hooks: None,
+            wheel: None,
+            profile: None,
+            platform: PlatformSections::default(),
         }
     }
 }
 
-fn load_config(config_path: &PathBuf) -> ProjectConfig {
-    match ProjectConfig::from_file(config_path) {
-        Ok(config) => config,
-        Err(e) => {
-            eprintln!(
-                "Warning: Failed to load config, using defaults. Error: {}",
-                e
-            );
-            ProjectConfig::default()
+/// A value of the effective configuration and the layer that set it.
+#[derive(Debug)]
+pub struct EffectiveValue {
+    pub value: toml::Value,
+    pub origin: String,
+}
+
+/// The merged configuration with the origin of every value, keyed by dotted path.
+pub struct LayeredConfig {
+    pub config: ProjectConfig,
+    pub values: BTreeMap<String, EffectiveValue>,
+    // Every `[profiles.<name>]` defined by any layer, selected or not
+    pub profiles: Vec<String>,
+}
+
+// Reads one configuration file as a layer. It is checked on its own first so errors point
+// into the file it came from, every key is optional since lower layers fill in the rest.
+fn read_layer(path: &Path) -> Result<Option<toml::Table>, String> {
+    let raw = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if path.file_name().and_then(|s| s.to_str()) == Some("pyproject.toml") {
+        let _: PyProject = toml::from_str(&raw).map_err(|e| describe_error(path, e))?;
+        let doc: toml::Table = toml::from_str(&raw).map_err(|e| describe_error(path, e))?;
+        Ok(doc
+            .get("tool")
+            .and_then(|tool| tool.get("pycrucible"))
+            .and_then(|section| section.as_table())
+            .cloned())
+    } else {
+        let _: ProjectConfigFile = toml::from_str(&raw).map_err(|e| describe_error(path, e))?;
+        toml::from_str(&raw)
+            .map(Some)
+            .map_err(|e| describe_error(path, e))
+    }
+}
+
+fn join_path(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+// Dotted paths of all values below `value`, arrays and empty tables count as single values
+fn collect_leaves<'a>(
+    prefix: &str,
+    value: &'a toml::Value,
+    leaves: &mut Vec<(String, &'a toml::Value)>,
+) {
+    match value {
+        toml::Value::Table(table) if prefix.is_empty() || !table.is_empty() => {
+            for (key, value) in table {
+                collect_leaves(&join_path(prefix, key), value, leaves);
+            }
         }
+        _ => leaves.push((prefix.to_string(), value)),
     }
 }
 
-pub fn load_project_config(source_dir: &PathBuf) -> ProjectConfig {
-    // Is there pycrucible.toml in the source directory?
-    if let Ok(path) = source_dir.join("pycrucible.toml").canonicalize()
-        && path.exists()
-    {
-        debug_println!("[config] using pycrucible.toml");
-        return load_config(&path);
+// Values that are either a string or a table naming one variant, like `entrypoint = { module
+// = "app" }`. Merging them key by key would combine the variants of two layers into one table.
+fn replaced_whole(path: &str) -> bool {
+    let mut parts: Vec<&str> = path.split('.').collect();
+    // `[profiles.<name>]` and `[platform.<name>]` hold the same keys as the top level
+    while parts.len() > 2 && matches!(parts[0], "profiles" | "platform") {
+        parts.drain(..2);
     }
+    matches!(
+        parts.as_slice(),
+        ["entrypoint"] | ["options", "venv"] | ["entrypoints" | "hooks" | "env", _]
+    )
+}
 
-    let pyproject = source_dir.join("pyproject.toml").canonicalize();
-    if let Ok(pyproject) = pyproject
-        && pyproject.exists()
-    {
-        match ProjectConfig::from_pyproject(&pyproject) {
-            Ok(cfg) => {
-                debug_println!("[config] using [tool.pycrucible] in pyproject.toml");
-                return cfg;
+// Tables are merged key by key, anything else (arrays included) and the values of
+// `replaced_whole` are replaced by the higher layer
+fn merge_layer(
+    target: &mut toml::Table,
+    layer: toml::Table,
+    prefix: &str,
+    origin: &str,
+    origins: &mut BTreeMap<String, String>,
+) {
+    for (key, value) in layer {
+        // `entry` is an alias, keep a single key so later layers override earlier ones
+        let key = if prefix.is_empty() && key == "entry" {
+            "entrypoint".to_string()
+        } else {
+            key
+        };
+        let path = join_path(prefix, &key);
+        match (target.get_mut(&key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(incoming))
+                if !replaced_whole(&path) =>
+            {
+                merge_layer(existing, incoming, &path, origin, origins);
+            }
+            (_, value) => {
+                let nested = format!("{}.", path);
+                origins.retain(|p, _| p != &path && !p.starts_with(&nested));
+                let mut leaves = Vec::new();
+                collect_leaves(&path, &value, &mut leaves);
+                for (leaf, _) in leaves {
+                    origins.insert(leaf, origin.to_string());
+                }
+                target.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Builds the effective configuration from, in increasing precedence: the built-in defaults,
+/// `[tool.pycrucible]` in pyproject.toml, pycrucible.toml, an explicit `config_file`, the
+/// selected `profile` and `overrides` (usually from command line flags).
+pub fn load_layered_config(
+    source_dir: &Path,
+    config_file: Option<&Path>,
+    profile: Option<&str>,
+    overrides: toml::Table,
+) -> Result<LayeredConfig, String> {
+    let mut origins = BTreeMap::new();
+    let mut merged = toml::Table::new();
+    let mut defaults =
+        toml::Table::try_from(ProjectConfig::default()).map_err(|e| e.to_string())?;
+    // Only defaulted once all layers are merged, named entrypoints make it optional
+    defaults.remove("entrypoint");
+    merge_layer(&mut merged, defaults, "", "default", &mut origins);
+
+    let files = [
+        (source_dir.join("pyproject.toml"), "pyproject.toml"),
+        (source_dir.join("pycrucible.toml"), "pycrucible.toml"),
+    ];
+    for (path, origin) in files {
+        if !path.is_file() {
+            continue;
+        }
+        match read_layer(&path)? {
+            Some(layer) => {
+                debug_println!("[config] applying {}", origin);
+                merge_layer(&mut merged, layer, "", origin, &mut origins);
             }
-            Err(e) => {
-                debug_println!(
-                    "[config] pyproject.toml found but no usable [tool.pycrucible] - {}",
-                    e
-                );
+            None => {
+                debug_println!("[config] pyproject.toml found but no [tool.pycrucible] section")
             }
         }
     }
 
-    // No config file found, use built-in defaults
-    debug_println!("[config] using built-in defaults");
-    ProjectConfig::default()
+    if let Some(config_file) = config_file {
+        let layer = read_layer(config_file)?
+            .ok_or_else(|| format!("No [tool.pycrucible] section in {}", config_file.display()))?;
+        let origin = format!("--config {}", config_file.display());
+        debug_println!("[config] applying {}", origin);
+        merge_layer(&mut merged, layer, "", &origin, &mut origins);
+    }
+
+    // Profiles may be spread over several files, so one is only picked once all files are merged
+    let profiles = merged.remove("profiles");
+    origins.retain(|path, _| !path.starts_with("profiles."));
+    let profile_names: Vec<String> = profiles
+        .as_ref()
+        .and_then(|profiles| profiles.as_table())
+        .map(|profiles| profiles.keys().cloned().collect())
+        .unwrap_or_default();
+    if let Some(name) = profile {
+        let overlay = profiles
+            .as_ref()
+            .and_then(|profiles| profiles.get(name))
+            .and_then(|overlay| overlay.as_table())
+            .cloned()
+            .ok_or_else(|| {
+                format!(
+                    "Unknown profile `{}`, available profiles: {}",
+                    name,
+                    if profile_names.is_empty() {
+                        "none".to_string()
+                    } else {
+                        profile_names.join(", ")
+                    }
+                )
+            })?;
+        debug_println!("[config] applying profile {}", name);
+        merge_layer(
+            &mut merged,
+            overlay,
+            "",
+            &format!("profile {}", name),
+            &mut origins,
+        );
+        let mut selected = toml::Table::new();
+        selected.insert("profile".into(), name.into());
+        merge_layer(&mut merged, selected, "", "--profile", &mut origins);
+    }
+
+    if !overrides.is_empty() {
+        debug_println!("[config] applying command line overrides");
+        merge_layer(&mut merged, overrides, "", "command line", &mut origins);
+    }
+
+    let has_entrypoints = merged
+        .get("entrypoints")
+        .and_then(|entrypoints| entrypoints.as_table())
+        .is_some_and(|entrypoints| !entrypoints.is_empty());
+    if !merged.contains_key("entrypoint") && !has_entrypoints {
+        let mut default = toml::Table::new();
+        default.insert("entrypoint".into(), "main.py".into());
+        merge_layer(&mut merged, default, "", "default", &mut origins);
+    }
+
+    let merged = toml::Value::Table(merged);
+    let mut leaves = Vec::new();
+    collect_leaves("", &merged, &mut leaves);
+    let values = leaves
+        .into_iter()
+        .map(|(path, value)| {
+            let origin = origins.remove(&path).unwrap_or_default();
+            let value = value.clone();
+            (path, EffectiveValue { value, origin })
+        })
+        .collect();
+
+    let config: ProjectConfig = merged
+        .try_into()
+        .map_err(|e: toml::de::Error| format!("Invalid configuration: {}", e.message()))?;
+    if let Some(source) = &config.source
+        && source.repository.is_empty()
+    {
+        return Err(
+            "Invalid configuration: `source.repository` is required when [source] is set".into(),
+        );
+    }
+    if let Some(source) = &config.source {
+        source.validate()?;
+    }
+    Ok(LayeredConfig {
+        config,
+        values,
+        profiles: profile_names,
+    })
+}
+
+/// Loads the layered configuration of `source_dir` without an explicit config file or overrides.
+pub fn load_project_config(source_dir: &Path) -> Result<ProjectConfig, String> {
+    load_layered_config(source_dir, None, None, toml::Table::new()).map(|layered| layered.config)
 }
 
 #[cfg(test)]
@@ -219,7 +1143,7 @@ mod tests {
     #[test]
     fn test_file_patterns_default() {
         let patterns = FilePatterns::default();
-        assert!(patterns.include.contains(&"**/*.py".to_string()));
+        assert!(patterns.include.contains(&"**/*".to_string()));
         assert!(patterns.exclude.contains(&".venv/**/*".to_string()));
     }
 
@@ -227,7 +1151,7 @@ mod tests {
     fn test_package_config_default() {
         let pkg = PackageConfig::default();
         assert_eq!(pkg.entrypoint, "main.py");
-        assert!(pkg.patterns.include.contains(&"**/*.py".to_string()));
+        assert!(pkg.patterns.include.contains(&"**/*".to_string()));
     }
 
     #[test]
@@ -239,7 +1163,7 @@ mod tests {
                 .package
                 .patterns
                 .include
-                .contains(&"**/*.py".to_string())
+                .contains(&"**/*".to_string())
         );
         assert!(config.source.is_none());
         assert!(config.uv.is_none());
@@ -291,7 +1215,7 @@ mod tests {
         let mut file = File::create(&file_path).unwrap();
         file.write_all(toml_content.as_bytes()).unwrap();
 
-        let config = ProjectConfig::from_pyproject(&file_path).unwrap();
+        let config = ProjectConfig::from_pyproject(&file_path).unwrap().unwrap();
         assert_eq!(config.package.entrypoint, "main2.py");
         assert!(
             config
@@ -312,7 +1236,7 @@ mod tests {
         let mut file = File::create(&config_path).unwrap();
         file.write_all(toml_content.as_bytes()).unwrap();
 
-        let config = load_project_config(&dir.path().to_path_buf());
+        let config = load_project_config(dir.path()).unwrap();
         assert_eq!(config.package.entrypoint, "run.py");
     }
 
@@ -327,14 +1251,14 @@ mod tests {
         let mut file = File::create(&config_path).unwrap();
         file.write_all(toml_content.as_bytes()).unwrap();
 
-        let config = load_project_config(&dir.path().to_path_buf());
+        let config = load_project_config(dir.path()).unwrap();
         assert_eq!(config.package.entrypoint, "main3.py");
     }
 
     #[test]
     fn test_load_project_config_defaults_when_no_config() {
         let dir = tempdir().unwrap();
-        let config = load_project_config(&dir.path().to_path_buf());
+        let config = load_project_config(dir.path()).unwrap();
         assert_eq!(config.package.entrypoint, "main.py");
     }
 
@@ -343,6 +1267,7 @@ mod tests {
         let source = SourceConfig::default();
         assert_eq!(source.repository, "");
         assert_eq!(source.update_strategy, Some("pull".to_string()));
+        assert!(source.trusted_signers.is_none());
     }
 
     #[test]
@@ -356,6 +1281,654 @@ mod tests {
         let hooks = Hooks::default();
         assert!(hooks.pre_run.is_none());
         assert!(hooks.post_run.is_none());
+        assert_eq!(hooks.all().count(), 0);
+    }
+
+    #[test]
+    fn test_hooks_from_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pycrucible.toml");
+        fs::write(
+            &path,
+            r#"
+                [hooks]
+                pre_run = "scripts/setup.py"
+                post_run = [
+                    "scripts/report.py",
+                    { module = "app.cleanup", args = ["--all"], timeout = 30 },
+                    { command = "notify-send", args = ["done"], continue_on_error = true },
+                ]
+                on_error = { script = "scripts/on_error.py" }
+            "#,
+        )
+        .unwrap();
+        let config = ProjectConfig::from_file(&path).unwrap();
+        let hooks = config.hooks.unwrap();
+
+        let all: Vec<(&str, HookKind)> = hooks
+            .all()
+            .map(|(point, hook)| (point, hook.kind().unwrap()))
+            .collect();
+        assert_eq!(
+            all,
+            vec![
+                ("pre_run", HookKind::Script("scripts/setup.py")),
+                ("on_error", HookKind::Script("scripts/on_error.py")),
+                ("post_run", HookKind::Script("scripts/report.py")),
+                ("post_run", HookKind::Module("app.cleanup")),
+                ("post_run", HookKind::Command("notify-send")),
+            ]
+        );
+        let post_run = hooks.post_run.as_ref().unwrap().hooks();
+        assert_eq!(post_run[1].args(), ["--all"]);
+        assert_eq!(post_run[1].timeout(), Some(30));
+        assert!(post_run[2].continue_on_error());
+
+        let ambiguous = HookSpec::Entry(HookEntry {
+            script: Some("a.py".to_string()),
+            command: Some("b".to_string()),
+            ..Default::default()
+        });
+        assert!(ambiguous.kind().is_err());
+    }
+
+    #[test]
+    fn test_unknown_key_is_rejected_with_suggestion() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("pycrucible.toml");
+        fs::write(
+            &file_path,
+            "entrypoint = \"app.py\"\n\n[hook]\npre_run = \"x.py\"\n",
+        )
+        .unwrap();
+
+        let err = ProjectConfig::from_file(&file_path).unwrap_err();
+        assert!(err.contains("line 3"), "{}", err);
+        assert!(err.contains("unknown field `hook`"), "{}", err);
+        assert!(err.contains("did you mean `hooks`?"), "{}", err);
+    }
+
+    #[test]
+    fn test_unknown_key_in_pyproject_points_into_pyproject() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("pyproject.toml");
+        fs::write(
+            &file_path,
+            "[project]\nname = \"app\"\n\n[tool.pycrucible]\nentry_point = \"app.py\"\n",
+        )
+        .unwrap();
+
+        let err = load_project_config(dir.path()).unwrap_err();
+        assert!(err.contains("line 5"), "{}", err);
+        assert!(err.contains("did you mean `entrypoint`?"), "{}", err);
+    }
+
+    #[test]
+    fn test_pyproject_without_section_uses_defaults() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("pyproject.toml"),
+            "[project]\nname = \"app\"\n[tool.ruff]\nline-length = 100\n",
+        )
+        .unwrap();
+        let config = load_project_config(dir.path()).unwrap();
+        assert_eq!(config.package.entrypoint, "main.py");
+    }
+
+    #[test]
+    fn test_serialized_config_round_trips_strictly() {
+        let config = ProjectConfig {
+            source: Some(SourceConfig {
+                repository: "https://example.com/repo.git".to_string(),
+                ..Default::default()
+            }),
+            env: Some(EnvConfig {
+                variables: Some(HashMap::from([
+                    ("FOO".to_string(), EnvValue::Value("bar".to_string())),
+                    (
+                        "PATH".to_string(),
+                        EnvValue::Entry(EnvEntry {
+                            prepend: vec!["${PYCRUCIBLE_PROJECT_DIR}/bin".to_string()],
+                            ..Default::default()
+                        }),
+                    ),
+                ])),
+            }),
+            ..Default::default()
+        };
+        let serialized = toml::to_string(&config).unwrap();
+        let parsed: ProjectConfig = toml::from_str(&serialized).unwrap();
+        assert_eq!(parsed.package.entrypoint, "main.py");
+        assert!(parsed.source.is_some());
+        assert_eq!(
+            parsed.env.unwrap().variables.unwrap()["PATH"],
+            EnvValue::Entry(EnvEntry {
+                prepend: vec!["${PYCRUCIBLE_PROJECT_DIR}/bin".to_string()],
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_json_schema_rejects_unknown_keys() {
+        let schema = ProjectConfig::json_schema().to_value();
+        assert_eq!(schema["additionalProperties"], false);
+        assert!(schema["properties"]["entrypoint"].is_object());
+    }
+
+    #[test]
+    fn test_layered_config_precedence() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("pyproject.toml"),
+            r#"
+                [tool.pycrucible]
+                entry = "from_pyproject.py"
+                [tool.pycrucible.options]
+                debug = true
+                delete_after_run = true
+                [tool.pycrucible.env]
+                A = "pyproject"
+                B = "pyproject"
+            "#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("pycrucible.toml"),
+            r#"
+                entrypoint = "from_pycrucible.py"
+                [patterns]
+                include = ["app/**/*.py"]
+                [env]
+                B = "pycrucible"
+            "#,
+        )
+        .unwrap();
+        let extra = dir.path().join("release.toml");
+        fs::write(&extra, "[options]\ndebug = false\n").unwrap();
+
+        let mut overrides = toml::Table::new();
+        overrides.insert(
+            "options".into(),
+            toml::Table::from_iter([("uv_version".to_string(), "0.9.0".into())]).into(),
+        );
+        let layered = load_layered_config(dir.path(), Some(&extra), None, overrides).unwrap();
+        let config = &layered.config;
+
+        assert_eq!(config.package.entrypoint, "from_pycrucible.py");
+        assert_eq!(config.package.patterns.include, vec!["app/**/*.py"]);
+        // Exclude patterns were not touched by any file
+        assert!(
+            config
+                .package
+                .patterns
+                .exclude
+                .contains(&".venv/**/*".to_string())
+        );
+        assert!(!config.options.debug);
+        assert!(config.options.delete_after_run);
+        assert_eq!(config.options.uv_version, "0.9.0");
+        let env = config.env.as_ref().unwrap().variables.as_ref().unwrap();
+        assert_eq!(env["A"], EnvValue::Value("pyproject".to_string()));
+        assert_eq!(env["B"], EnvValue::Value("pycrucible".to_string()));
+
+        let origin = |path: &str| layered.values[path].origin.clone();
+        assert_eq!(origin("entrypoint"), "pycrucible.toml");
+        assert_eq!(origin("options.delete_after_run"), "pyproject.toml");
+        assert_eq!(
+            origin("options.debug"),
+            format!("--config {}", extra.display())
+        );
+        assert_eq!(origin("options.uv_version"), "command line");
+        assert_eq!(origin("options.extract_to_temp"), "default");
+        assert_eq!(origin("env.B"), "pycrucible.toml");
+    }
+
+    #[test]
+    fn test_source_paths_stay_inside_the_checkout() {
+        let dir = tempdir().unwrap();
+        let write = |source: &str| {
+            fs::write(
+                dir.path().join("pycrucible.toml"),
+                format!(
+                    "[source]\nrepository = \"https://example.com/app.git\"\n{}\n",
+                    source
+                ),
+            )
+            .unwrap();
+        };
+
+        for valid in [
+            "subdirectory = \"apps/tool\"",
+            "subdirectory = \"./app\"",
+            "sparse_paths = [\"shared\", \"docs/api\"]",
+        ] {
+            write(valid);
+            assert!(load_project_config(dir.path()).is_ok(), "{}", valid);
+        }
+        for invalid in [
+            "subdirectory = \"/etc\"",
+            "subdirectory = \"../outside\"",
+            "subdirectory = \"app/../../outside\"",
+            "subdirectory = \"..\\\\outside\"",
+            "sparse_paths = [\"app\", \"../secrets\"]",
+        ] {
+            write(invalid);
+            let err = load_project_config(dir.path()).unwrap_err();
+            assert!(
+                err.contains("inside the repository"),
+                "{}: {}",
+                invalid,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_tagged_values_replaced_whole() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("pyproject.toml"),
+            r#"
+                [tool.pycrucible]
+                entrypoint = { module = "app.cli" }
+                [tool.pycrucible.entrypoints]
+                admin = { module = "app.admin" }
+                [tool.pycrucible.hooks]
+                pre_run = { script = "setup.py", timeout = 30 }
+                [tool.pycrucible.env]
+                PATH = { value = "/opt/app/bin" }
+                [tool.pycrucible.options]
+                venv = { path = "/opt/app/venv" }
+            "#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("pycrucible.toml"),
+            r#"
+                entrypoint = { script = "main.py" }
+                [entrypoints]
+                admin = { callable = "app.admin:main" }
+                [hooks]
+                pre_run = { command = "make" }
+                [env]
+                PATH = { prepend = ["bin"] }
+
+                [profiles.prod]
+                entrypoint = { console_script = "app" }
+                [profiles.prod.hooks]
+                pre_run = { module = "app.migrate" }
+
+                [platform.windows.hooks]
+                pre_run = { script = "setup_windows.py" }
+            "#,
+        )
+        .unwrap();
+
+        let layered = load_layered_config(dir.path(), None, None, toml::Table::new()).unwrap();
+        let config = layered.config;
+        assert_eq!(
+            config.package.entrypoint.kind().unwrap(),
+            EntrypointKind::Script("main.py")
+        );
+        assert_eq!(
+            config.package.entrypoints["admin"].kind().unwrap(),
+            EntrypointKind::Callable {
+                module: "app.admin",
+                function: "main"
+            }
+        );
+        let hooks = config.hooks.as_ref().unwrap();
+        let pre_run = &hooks.pre_run.as_ref().unwrap().hooks()[0];
+        assert_eq!(pre_run.kind().unwrap(), HookKind::Command("make"));
+        assert_eq!(pre_run.timeout(), None);
+        assert_eq!(
+            config.env.as_ref().unwrap().variables.as_ref().unwrap()["PATH"],
+            EnvValue::Entry(EnvEntry {
+                prepend: vec!["bin".to_string()],
+                ..Default::default()
+            })
+        );
+        assert!(!layered.values.contains_key("entrypoint.module"));
+
+        // Platform overlays replace hooks of the same point
+        let windows = config.for_platforms(&["windows"]).unwrap();
+        let pre_run = &windows
+            .hooks
+            .as_ref()
+            .unwrap()
+            .pre_run
+            .as_ref()
+            .unwrap()
+            .hooks()[0];
+        assert_eq!(
+            pre_run.kind().unwrap(),
+            HookKind::Script("setup_windows.py")
+        );
+
+        let prod = load_layered_config(dir.path(), None, Some("prod"), toml::Table::new())
+            .unwrap()
+            .config;
+        assert_eq!(
+            prod.package.entrypoint.kind().unwrap(),
+            EntrypointKind::ConsoleScript("app")
+        );
+        let pre_run = &prod
+            .hooks
+            .as_ref()
+            .unwrap()
+            .pre_run
+            .as_ref()
+            .unwrap()
+            .hooks()[0];
+        assert_eq!(pre_run.kind().unwrap(), HookKind::Module("app.migrate"));
+
+        // `--console-script` over a table entrypoint
+        let overrides = toml::Table::from_iter([(
+            "entrypoint".to_string(),
+            toml::Table::from_iter([("console_script".to_string(), "tool".into())]).into(),
+        )]);
+        let config = load_layered_config(dir.path(), None, None, overrides)
+            .unwrap()
+            .config;
+        assert_eq!(
+            config.package.entrypoint.kind().unwrap(),
+            EntrypointKind::ConsoleScript("tool")
+        );
+
+        // A profile spread over two files replaces its entrypoint whole as well
+        let extra = dir.path().join("release.toml");
+        fs::write(
+            &extra,
+            "[profiles.prod]\nentrypoint = { module = \"app.release\" }\n",
+        )
+        .unwrap();
+        let prod = load_layered_config(dir.path(), Some(&extra), Some("prod"), toml::Table::new())
+            .unwrap()
+            .config;
+        assert_eq!(
+            prod.package.entrypoint.kind().unwrap(),
+            EntrypointKind::Module("app.release")
+        );
+        assert!(replaced_whole("options.venv"));
+        assert!(!replaced_whole("options"));
+        assert!(!replaced_whole("hooks"));
+    }
+
+    #[test]
+    fn test_layered_config_profiles() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("pycrucible.toml"),
+            r#"
+                entrypoint = "app.py"
+                [options]
+                debug = true
+                [env]
+                APP_ENV = "dev"
+
+                [profiles.prod.options]
+                debug = false
+                [profiles.prod.env]
+                APP_ENV = "prod"
+                [profiles.prod.source]
+                repository = "https://example.com/app.git"
+                branch = "release"
+            "#,
+        )
+        .unwrap();
+
+        let layered =
+            load_layered_config(dir.path(), None, Some("prod"), toml::Table::new()).unwrap();
+        let config = &layered.config;
+        assert!(!config.options.debug);
+        assert_eq!(config.profile.as_deref(), Some("prod"));
+        assert_eq!(
+            config.env.as_ref().unwrap().variables.as_ref().unwrap()["APP_ENV"],
+            EnvValue::Value("prod".to_string())
+        );
+        assert_eq!(
+            config.source.as_ref().unwrap().branch.as_deref(),
+            Some("release")
+        );
+        assert_eq!(layered.values["options.debug"].origin, "profile prod");
+        assert!(
+            !layered
+                .values
+                .keys()
+                .any(|path| path.starts_with("profiles"))
+        );
+
+        // The profile name survives the round trip through the embedded config
+        let embedded = toml::to_string(config).unwrap();
+        let parsed: ProjectConfig = toml::from_str(&embedded).unwrap();
+        assert_eq!(parsed.profile.as_deref(), Some("prod"));
+
+        let without = load_project_config(dir.path()).unwrap();
+        assert!(without.options.debug);
+        assert!(without.profile.is_none());
+
+        let err = load_layered_config(dir.path(), None, Some("staging"), toml::Table::new())
+            .err()
+            .unwrap();
+        assert!(err.contains("Unknown profile `staging`, available profiles: prod"));
+    }
+
+    #[test]
+    fn test_platform_sections_resolved_per_host() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("pycrucible.toml"),
+            r#"
+                entrypoint = "app.py"
+                [uv]
+                args = ["--quiet"]
+                [env]
+                APP_ENV = "prod"
+                CACHE = "/var/cache/app"
+
+                [platform.unix.env]
+                CACHE = "/tmp/app"
+                [platform.macos.env]
+                CACHE = "${HOME}/Library/Caches/app"
+                [platform.windows.env]
+                CACHE = "${LOCALAPPDATA}/app"
+                [platform.windows.hooks]
+                pre_run = "scripts/setup_windows.py"
+                [platform.windows.uv]
+                args = ["--native-tls"]
+            "#,
+        )
+        .unwrap();
+
+        // The builder keeps every section, they survive the embedded config round trip
+        let config = load_project_config(dir.path()).unwrap();
+        assert!(config.platform.windows.is_some());
+        let embedded = toml::to_string(&config).unwrap();
+        let config: ProjectConfig = toml::from_str(&embedded).unwrap();
+
+        let variables = |config: &ProjectConfig, name: &str| {
+            config.env.as_ref().unwrap().variables.as_ref().unwrap()[name].clone()
+        };
+        let windows = ProjectConfig::from_file(&dir.path().join("pycrucible.toml"))
+            .unwrap()
+            .for_platforms(&["windows"])
+            .unwrap();
+        assert_eq!(
+            variables(&windows, "CACHE"),
+            EnvValue::Value("${LOCALAPPDATA}/app".to_string())
+        );
+        assert_eq!(
+            variables(&windows, "APP_ENV"),
+            EnvValue::Value("prod".to_string())
+        );
+        assert_eq!(
+            windows.hooks.unwrap().pre_run,
+            Some(HookList::One(HookSpec::Script(
+                "scripts/setup_windows.py".to_string()
+            )))
+        );
+        assert_eq!(windows.uv.unwrap().args.unwrap(), vec!["--native-tls"]);
+        assert!(windows.platform.is_empty());
+
+        let macos = config.for_platforms(&["unix", "macos"]).unwrap();
+        assert_eq!(
+            variables(&macos, "CACHE"),
+            EnvValue::Value("${HOME}/Library/Caches/app".to_string())
+        );
+        assert!(macos.hooks.is_none());
+        assert_eq!(macos.uv.unwrap().args.unwrap(), vec!["--quiet"]);
+    }
+
+    #[test]
+    fn test_unknown_platform_is_rejected_with_suggestion() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pycrucible.toml");
+        fs::write(&path, "[platform.window.env]\nA = \"1\"\n").unwrap();
+        let err = ProjectConfig::from_file(&path).unwrap_err();
+        assert!(err.contains("unknown field `window`"));
+        assert!(err.contains("help: did you mean `windows`?"));
+    }
+
+    #[test]
+    fn test_named_entrypoints() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pycrucible.toml");
+        fs::write(
+            &path,
+            "[entrypoints]\nserve = \"src/serve.py\"\nmigrate = \"src/migrate.py\"\n",
+        )
+        .unwrap();
+        let config = load_project_config(dir.path()).unwrap();
+        assert_eq!(config.package.entrypoint, "");
+        assert_eq!(config.package.default_entrypoint(), None);
+        assert_eq!(config.package.entrypoints["serve"], "src/serve.py");
+        let layered = load_layered_config(dir.path(), None, None, toml::Table::new()).unwrap();
+        assert!(!layered.values.contains_key("entrypoint"));
+
+        fs::write(
+            &path,
+            "entrypoint = \"serve\"\n[entrypoints]\nserve = \"src/serve.py\"\n",
+        )
+        .unwrap();
+        let config = load_project_config(dir.path()).unwrap();
+        assert_eq!(
+            config.package.default_entrypoint(),
+            Some(&"src/serve.py".into())
+        );
+
+        // The embedded config keeps both
+        let embedded = toml::to_string(&config).unwrap();
+        let parsed: ProjectConfig = toml::from_str(&embedded).unwrap();
+        assert_eq!(parsed.package.entrypoint, "serve");
+        assert_eq!(parsed.package.entrypoints.len(), 1);
+
+        fs::remove_file(&path).unwrap();
+        let config = load_project_config(dir.path()).unwrap();
+        assert_eq!(config.package.default_entrypoint(), Some(&"main.py".into()));
+    }
+
+    #[test]
+    fn test_entrypoint_kinds() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pycrucible.toml");
+        fs::write(
+            &path,
+            r#"
+                entrypoint = { module = "myapp.cli" }
+                [entrypoints]
+                serve = { callable = "myapp.server:main" }
+                tool = { console_script = "myapp-tool" }
+                legacy = "scripts/run.py"
+                bad = { module = "myapp.cli", script = "main.py" }
+                broken = { callable = "myapp.server" }
+            "#,
+        )
+        .unwrap();
+        let config = ProjectConfig::from_file(&path).unwrap();
+        let package = &config.package;
+        let kind = |name: &str| package.entrypoints[name].kind();
+
+        assert_eq!(
+            package.entrypoint.kind(),
+            Ok(EntrypointKind::Module("myapp.cli"))
+        );
+        assert_eq!(
+            kind("serve"),
+            Ok(EntrypointKind::Callable {
+                module: "myapp.server",
+                function: "main"
+            })
+        );
+        assert_eq!(
+            kind("tool"),
+            Ok(EntrypointKind::ConsoleScript("myapp-tool"))
+        );
+        assert_eq!(kind("legacy"), Ok(EntrypointKind::Script("scripts/run.py")));
+        assert!(kind("bad").unwrap_err().contains("exactly one of"));
+        assert!(
+            kind("broken")
+                .unwrap_err()
+                .contains("package.module:function")
+        );
+        assert_eq!(
+            EntrypointSpec::from("app-1.0-py3-none-any.whl").kind(),
+            Ok(EntrypointKind::Wheel("app-1.0-py3-none-any.whl"))
+        );
+        assert_eq!(package.entrypoint.to_string(), "-m myapp.cli");
+    }
+
+    #[test]
+    fn test_layered_config_defaults_uv_version() {
+        let dir = tempdir().unwrap();
+        let config = load_project_config(dir.path()).unwrap();
+        assert_eq!(config.options.uv_version, DEFAULT_UV_VERSION);
+    }
+
+    #[test]
+    fn test_lock_mode_from_file() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("pycrucible.toml");
+        fs::write(&file_path, "entry = \"app.py\"\n").unwrap();
+        let config = ProjectConfig::from_file(&file_path).unwrap();
+        assert_eq!(config.options.lock_mode, LockMode::Locked);
+
+        fs::write(
+            &file_path,
+            "entry = \"app.py\"\n[options]\nlock_mode = \"frozen\"\n",
+        )
+        .unwrap();
+        let config = ProjectConfig::from_file(&file_path).unwrap();
+        assert_eq!(config.options.lock_mode, LockMode::Frozen);
+    }
+
+    #[test]
+    fn test_venv_location_from_file() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("pycrucible.toml");
+        fs::write(&file_path, "[options]\nvenv = \"persistent\"\n").unwrap();
+        let config = ProjectConfig::from_file(&file_path).unwrap();
+        assert_eq!(
+            config.options.venv,
+            VenvLocation::Mode(VenvMode::Persistent)
+        );
+
+        fs::write(
+            &file_path,
+            "[options]\nvenv = { path = \"envs/app\" }\nuv_cache_dir = \"cache\"\n",
+        )
+        .unwrap();
+        let config = ProjectConfig::from_file(&file_path).unwrap();
+        assert_eq!(
+            config.options.venv,
+            VenvLocation::Path {
+                path: "envs/app".to_string()
+            }
+        );
+        assert_eq!(config.options.uv_cache_dir.as_deref(), Some("cache"));
+
+        fs::write(&file_path, "[options]\nvenv = \"global\"\n").unwrap();
+        assert!(ProjectConfig::from_file(&file_path).is_err());
     }
 
     #[test]
@@ -2,6 +2,7 @@
 
 use std::fs;
 use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
 
 pub const FOOTER_SIZE: usize = 16; // 8 offset + 1 flag + 7 magic
 pub const MAGIC_BYTES: &[u8] = b"PYCRUCI"; // 7 bytes
@@ -28,8 +29,12 @@ pub fn create_footer(extract_to_temp: bool, offset: u64) -> Vec<u8> {
 }
 
 pub fn read_footer() -> io::Result<PayloadInfo> {
-    let exe_path = std::env::current_exe()?;
-    let mut file = fs::File::open(&exe_path)?;
+    read_footer_from(&std::env::current_exe()?)
+}
+
+/// Reads the footer of any PyCrucible binary, not just the running one.
+pub fn read_footer_from(exe_path: &Path) -> io::Result<PayloadInfo> {
+    let mut file = fs::File::open(exe_path)?;
     let file_size = file.metadata()?.len();
 
     if file_size < FOOTER_SIZE as u64 {
@@ -1,35 +1,426 @@
-use reqwest::blocking::get;
-use std::io::Cursor;
+use crate::config::ToolOptions;
+use crate::debug_println;
+use crate::uv_handler::progress::DownloadProgress;
+use reqwest::blocking::Client;
+use reqwest::header::RANGE;
+use reqwest::{Certificate, NoProxy, Proxy, StatusCode};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::time::Duration;
 
-pub enum Archive<'a> {
-    Zip(Cursor<Vec<u8>>),
-    TarGz(&'a mut reqwest::blocking::Response),
-}
+pub const DEFAULT_UV_RELEASES_URL: &str = "https://github.com/astral-sh/uv/releases/download";
 
-pub fn build_release_url(version: &str, target: &str) -> String {
-    let ext = if target.contains("windows") {
+/// Extension of the uv release archive for `target`: zip on Windows, tar.gz elsewhere.
+pub fn archive_extension(target: &str) -> &'static str {
+    if target.contains("windows") {
         "zip"
     } else {
         "tar.gz"
+    }
+}
+
+/// Builds the download URL for the uv release asset.
+///
+/// `mirror` is either a base URL laid out like GitHub releases (`{base}/{version}/uv-{target}.{ext}`)
+/// or a full template containing `{version}`, `{target}` and `{ext}` placeholders.
+pub fn build_release_url(version: &str, target: &str, mirror: Option<&str>) -> String {
+    let ext = archive_extension(target);
+
+    match mirror {
+        Some(template) if template.contains('{') => template
+            .replace("{version}", version)
+            .replace("{target}", target)
+            .replace("{ext}", ext),
+        Some(base) => format!(
+            "{base}/{v}/uv-{target}.{ext}",
+            base = base.trim_end_matches('/'),
+            v = version
+        ),
+        None => format!(
+            "{base}/{v}/uv-{target}.{ext}",
+            base = DEFAULT_UV_RELEASES_URL,
+            v = version
+        ),
+    }
+}
+
+/// Builds the HTTP client used for uv downloads.
+/// `HTTP(S)_PROXY` and `NO_PROXY` are honored unless an explicit proxy is configured.
+pub fn build_client(options: &ToolOptions) -> Result<Client, Box<dyn std::error::Error>> {
+    let mut builder = Client::builder();
+
+    if let Some(proxy) = &options.proxy {
+        builder = builder.proxy(Proxy::all(proxy)?.no_proxy(NoProxy::from_env()));
+    }
+
+    if let Some(ca_bundle) = &options.ca_bundle {
+        let pem = std::fs::read(ca_bundle)
+            .map_err(|e| format!("Failed to read CA bundle {}: {}", ca_bundle, e))?;
+        builder = builder.tls_certs_merge(Certificate::from_pem_bundle(&pem)?);
+    }
+
+    Ok(builder.build()?)
+}
+
+/// How often failed downloads are retried, with the delay doubling after every attempt.
+pub struct RetryPolicy {
+    pub attempts: u32,
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 5,
+            base_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+enum AttemptError {
+    // Network hiccups and server side errors, worth another try
+    Retryable(String),
+    Fatal(String),
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+}
+
+fn with_retries<T>(
+    policy: &RetryPolicy,
+    url: &str,
+    mut attempt: impl FnMut() -> Result<T, AttemptError>,
+) -> Result<T, Box<dyn std::error::Error>> {
+    let mut tries = 0;
+    loop {
+        tries += 1;
+        match attempt() {
+            Ok(value) => return Ok(value),
+            Err(AttemptError::Fatal(e)) => return Err(e.into()),
+            Err(AttemptError::Retryable(e)) if tries >= policy.attempts => {
+                return Err(
+                    format!("Download of {} failed after {} attempts: {}", url, tries, e).into(),
+                );
+            }
+            Err(AttemptError::Retryable(e)) => {
+                let delay = policy.base_delay * 2u32.pow(tries - 1);
+                eprintln!(
+                    "Download of {} failed ({}), retrying in {:?} ...",
+                    url, e, delay
+                );
+                std::thread::sleep(delay);
+            }
+        }
+    }
+}
+
+fn download_attempt(
+    client: &Client,
+    url: &str,
+    part_path: &Path,
+    progress: &mut DownloadProgress,
+) -> Result<(), AttemptError> {
+    let existing = fs::metadata(part_path).map(|m| m.len()).unwrap_or(0);
+    let mut request = client.get(url);
+    if existing > 0 {
+        debug_println!(
+            "[uv_handler.download] - Resuming {} from byte {}",
+            url,
+            existing
+        );
+        request = request.header(RANGE, format!("bytes={}-", existing));
+    }
+    let mut response = request
+        .send()
+        .map_err(|e| AttemptError::Retryable(e.to_string()))?;
+
+    let status = response.status();
+    if status == StatusCode::RANGE_NOT_SATISFIABLE && existing > 0 {
+        // Nothing left to fetch, the checksum decides whether the part file is any good
+        return Ok(());
+    }
+    if is_retryable_status(status) {
+        return Err(AttemptError::Retryable(format!("HTTP {}", status)));
+    }
+    if !status.is_success() {
+        return Err(AttemptError::Fatal(format!("HTTP {} for {}", status, url)));
+    }
+
+    let (file, start) = if status == StatusCode::PARTIAL_CONTENT {
+        (OpenOptions::new().append(true).open(part_path), existing)
+    } else {
+        // The server ignored the range request, start over
+        (fs::File::create(part_path), 0)
     };
+    let mut file = file.map_err(|e| AttemptError::Fatal(e.to_string()))?;
+    let total = response.content_length().map(|len| len + start);
+    progress.start(start, total);
 
-    format!(
-        "https://github.com/astral-sh/uv/releases/download/{v}/uv-{target}.{ext}",
-        v = version
-    )
+    let mut downloaded = start;
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = response
+            .read(&mut buffer)
+            .map_err(|e| AttemptError::Retryable(e.to_string()))?;
+        if read == 0 {
+            break;
+        }
+        file.write_all(&buffer[..read])
+            .map_err(|e| AttemptError::Fatal(e.to_string()))?;
+        downloaded += read as u64;
+        progress.advance(read as u64);
+    }
+
+    match total {
+        Some(total) if downloaded < total => Err(AttemptError::Retryable(format!(
+            "connection closed after {} of {} bytes",
+            downloaded, total
+        ))),
+        _ => Ok(()),
+    }
 }
 
-pub fn download(url: &str) -> Result<DownloadResult, Box<dyn std::error::Error>> {
-    let response = get(url)?.error_for_status()?;
-    if url.ends_with(".zip") {
-        let bytes = response.bytes()?.to_vec();
-        Ok(DownloadResult::Zip(Cursor::new(bytes)))
+/// Downloads `url` into `part_path`, resuming whatever is already there, and returns its contents.
+/// The part file is left in place so an interrupted download can be resumed later.
+pub fn download(
+    url: &str,
+    part_path: &Path,
+    options: &ToolOptions,
+    policy: &RetryPolicy,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if let Some(parent) = part_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let client = build_client(options)?;
+    let label = url.rsplit('/').next().unwrap_or(url).to_string();
+    let mut progress = DownloadProgress::new(&format!("Downloading {}", label));
+    with_retries(policy, url, || {
+        download_attempt(&client, url, part_path, &mut progress)
+    })?;
+    progress.finish();
+    Ok(fs::read(part_path)?)
+}
+
+/// Fetches the published `.sha256` file for a release asset.
+/// The file is formatted like `sha256sum` output: `<hex digest> *<file name>`.
+pub fn fetch_checksum(
+    url: &str,
+    options: &ToolOptions,
+    policy: &RetryPolicy,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let checksum_url = format!("{}.sha256", url);
+    let client = build_client(options)?;
+    let content = with_retries(policy, &checksum_url, || {
+        let response = client
+            .get(&checksum_url)
+            .send()
+            .map_err(|e| AttemptError::Retryable(e.to_string()))?;
+        let status = response.status();
+        if is_retryable_status(status) {
+            return Err(AttemptError::Retryable(format!("HTTP {}", status)));
+        }
+        if !status.is_success() {
+            return Err(AttemptError::Fatal(format!(
+                "Failed to fetch checksum from {}: HTTP {}",
+                checksum_url, status
+            )));
+        }
+        response
+            .text()
+            .map_err(|e| AttemptError::Retryable(e.to_string()))
+    })?;
+    parse_checksum(&content)
+        .ok_or_else(|| format!("Invalid checksum file at {}", checksum_url).into())
+}
+
+fn parse_checksum(content: &str) -> Option<String> {
+    let digest = content.split_whitespace().next()?;
+    if digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(digest.to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub fn verify_checksum(bytes: &[u8], expected: &str) -> Result<(), String> {
+    let actual = sha256_hex(bytes);
+    if actual.eq_ignore_ascii_case(expected.trim()) {
+        Ok(())
     } else {
-        Ok(DownloadResult::TarGz(response))
+        Err(format!(
+            "Checksum mismatch for uv archive: expected {}, got {}",
+            expected.trim(),
+            actual
+        ))
     }
 }
 
-pub enum DownloadResult {
-    Zip(Cursor<Vec<u8>>),
-    TarGz(reqwest::blocking::Response),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    // Serves one canned raw HTTP response per connection and records the request headers
+    fn serve(responses: Vec<Vec<u8>>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/uv.tar.gz", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&requests);
+        std::thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut head = String::new();
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap() == 0 || line == "\r\n" {
+                        break;
+                    }
+                    head.push_str(&line.to_ascii_lowercase());
+                }
+                recorded.lock().unwrap().push(head);
+                stream.write_all(&response).unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    fn no_delay() -> RetryPolicy {
+        RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_download_retries_server_errors() {
+        let (url, requests) = serve(vec![
+            b"HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                .to_vec(),
+            b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\nconnection: close\r\n\r\nhello".to_vec(),
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+        let part = dir.path().join("uv.tar.gz.part");
+
+        let bytes = download(&url, &part, &ToolOptions::default(), &no_delay()).unwrap();
+        assert_eq!(bytes, b"hello");
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_download_resumes_after_connection_drop() {
+        let (url, requests) = serve(vec![
+            // Promises ten bytes but hangs up after five
+            b"HTTP/1.1 200 OK\r\ncontent-length: 10\r\nconnection: close\r\n\r\nhello".to_vec(),
+            b"HTTP/1.1 206 Partial Content\r\ncontent-length: 5\r\nconnection: close\r\n\r\nworld"
+                .to_vec(),
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+        let part = dir.path().join("uv.tar.gz.part");
+
+        let bytes = download(&url, &part, &ToolOptions::default(), &no_delay()).unwrap();
+        assert_eq!(bytes, b"helloworld");
+        let requests = requests.lock().unwrap();
+        assert!(!requests[0].contains("range:"));
+        assert!(requests[1].contains("range: bytes=5-"));
+    }
+
+    #[test]
+    fn test_download_does_not_retry_client_errors() {
+        let (url, requests) = serve(vec![
+            b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n".to_vec(),
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+        let part = dir.path().join("uv.tar.gz.part");
+
+        assert!(download(&url, &part, &ToolOptions::default(), &no_delay()).is_err());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_build_release_url_default() {
+        assert_eq!(
+            build_release_url("0.9.21", "x86_64-unknown-linux-gnu", None),
+            "https://github.com/astral-sh/uv/releases/download/0.9.21/uv-x86_64-unknown-linux-gnu.tar.gz"
+        );
+    }
+
+    #[test]
+    fn test_build_release_url_mirror_base() {
+        assert_eq!(
+            build_release_url(
+                "0.9.21",
+                "x86_64-pc-windows-msvc",
+                Some("https://mirror.example.com/uv/")
+            ),
+            "https://mirror.example.com/uv/0.9.21/uv-x86_64-pc-windows-msvc.zip"
+        );
+    }
+
+    #[test]
+    fn test_build_release_url_mirror_template() {
+        assert_eq!(
+            build_release_url(
+                "0.9.21",
+                "aarch64-apple-darwin",
+                Some("https://artifacts.example.com/uv-{version}/{target}/uv.{ext}")
+            ),
+            "https://artifacts.example.com/uv-0.9.21/aarch64-apple-darwin/uv.tar.gz"
+        );
+    }
+
+    #[test]
+    fn test_archive_extension_follows_target() {
+        assert_eq!(archive_extension("x86_64-pc-windows-msvc"), "zip");
+        assert_eq!(archive_extension("aarch64-apple-darwin"), "tar.gz");
+        // Template mirrors need not end in the extension
+        let url = build_release_url(
+            "0.9.21",
+            "x86_64-pc-windows-msvc",
+            Some("https://artifacts.example.com/uv?version={version}&target={target}"),
+        );
+        assert!(!url.ends_with(".zip"));
+    }
+
+    #[test]
+    fn test_parse_checksum() {
+        let digest = "a".repeat(64);
+        assert_eq!(
+            parse_checksum(&format!("{} *uv-x86_64-unknown-linux-gnu.tar.gz\n", digest)),
+            Some(digest)
+        );
+        assert_eq!(parse_checksum("not-a-checksum"), None);
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        // sha256("abc")
+        let expected = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert!(verify_checksum(b"abc", expected).is_ok());
+        assert!(verify_checksum(b"abc", &expected.to_uppercase()).is_ok());
+        assert!(verify_checksum(b"abd", expected).is_err());
+    }
+
+    #[test]
+    fn test_build_client_rejects_missing_ca_bundle() {
+        let options = ToolOptions {
+            ca_bundle: Some("/nonexistent/ca.pem".to_string()),
+            ..Default::default()
+        };
+        assert!(build_client(&options).is_err());
+    }
 }
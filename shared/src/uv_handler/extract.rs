@@ -1,20 +1,25 @@
-use crate::uv_handler::download::Archive;
 use flate2::read::GzDecoder;
 use std::fs;
+use std::io::Cursor;
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use zip::ZipArchive;
 
-pub fn extract_uv<'a>(
-    archive: &mut Archive<'a>,
+pub enum Archive {
+    Zip(Cursor<Vec<u8>>),
+    TarGz(Cursor<Vec<u8>>),
+}
+
+pub fn extract_uv(
+    archive: &mut Archive,
     install_dir: &Path,
 ) -> Result<(), Box<dyn std::error::Error>> {
     fs::create_dir_all(install_dir)?;
 
     match archive {
         Archive::Zip(reader) => extract_zip(reader, install_dir),
-        Archive::TarGz(response) => extract_targz(*response, install_dir),
+        Archive::TarGz(reader) => extract_targz(reader, install_dir),
     }
 }
 
@@ -41,27 +46,26 @@ fn extract_zip(
 }
 
 fn extract_targz(
-    response: &mut reqwest::blocking::Response,
+    reader: &mut Cursor<Vec<u8>>,
     install_dir: &std::path::Path,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    // Streaming extraction: only extract the "uv" binary, skip all other entries.
-    let decoder = GzDecoder::new(response);
+    // Only extract the "uv" binary, skip all other entries.
+    let decoder = GzDecoder::new(reader);
     let mut archive = tar::Archive::new(decoder);
     let mut found = false;
     for entry in archive.entries()? {
         let mut entry = entry?;
-        if let Ok(path) = entry.path() {
-            if let Some(file_name) = path.file_name() {
-                if file_name == "uv" {
-                    let out_path = install_dir.join("uv");
-                    let mut out = std::fs::File::create(&out_path)?;
-                    std::io::copy(&mut entry, &mut out)?;
-                    #[cfg(unix)]
-                    std::fs::set_permissions(&out_path, std::fs::Permissions::from_mode(0o755))?;
-                    found = true;
-                    break;
-                }
-            }
+        if let Ok(path) = entry.path()
+            && let Some(file_name) = path.file_name()
+            && file_name == "uv"
+        {
+            let out_path = install_dir.join("uv");
+            let mut out = std::fs::File::create(&out_path)?;
+            std::io::copy(&mut entry, &mut out)?;
+            #[cfg(unix)]
+            std::fs::set_permissions(&out_path, std::fs::Permissions::from_mode(0o755))?;
+            found = true;
+            break;
         }
     }
     if !found {
@@ -1,30 +1,56 @@
+use crate::config::{SystemUvPolicy, ToolOptions};
 use crate::debug_println;
 use crate::uv_handler::download;
 use crate::uv_handler::extract;
 use crate::uv_handler::platform;
-use crate::{create_spinner_with_message, stop_and_persist_spinner_with_message};
+use crate::uv_handler::version::{UvVersionRequirement, query_uv_version};
+use std::io::Cursor;
 use std::path::Path;
 use std::path::PathBuf;
 
-pub fn install_uv(version: &str, install_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
-    let target = platform::target_triple();
-    let url = download::build_release_url(version, &target);
+pub fn install_uv(
+    version: &str,
+    install_dir: &Path,
+    options: &ToolOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let target = platform::target_triple()?;
+    let url = download::build_release_url(version, &target, options.uv_mirror.as_deref());
+    debug_println!("[uv_handler.install_uv] - Downloading uv from {}", url);
 
-    let mut download_result = download::download(&url)?;
-    match download_result {
-        download::DownloadResult::Zip(ref reader) => {
-            let mut archive = download::Archive::Zip(reader.clone());
-            extract::extract_uv(&mut archive, install_dir)?;
-        }
-        download::DownloadResult::TarGz(ref mut response) => {
-            let mut archive = download::Archive::TarGz(response);
-            extract::extract_uv(&mut archive, install_dir)?;
-        }
-    }
+    // Resolve the expected checksum first, unverified bytes never leave the part file
+    let policy = download::RetryPolicy::default();
+    let pinned = options
+        .uv_sha256
+        .as_ref()
+        .and_then(|pins| pins.get(&target));
+    let expected = match pinned {
+        Some(pinned) => pinned.clone(),
+        None => download::fetch_checksum(&url, options, &policy)?,
+    };
+
+    let asset_name = format!("uv-{}.{}", target, download::archive_extension(&target));
+    let part_path = install_dir.join(format!("{}.part", asset_name));
+    let bytes = download::download(&url, &part_path, options, &policy)?;
+    // Either way the part file is done with: verified bytes are extracted below and
+    // corrupt ones must not be resumed from
+    let _ = std::fs::remove_file(&part_path);
+    download::verify_checksum(&bytes, &expected)?;
+    debug_println!(
+        "[uv_handler.install_uv] - Verified uv archive checksum {}",
+        expected
+    );
+
+    // Template mirrors need not end in the file extension, the release format follows the target
+    let mut archive = if download::archive_extension(&target) == "zip" {
+        extract::Archive::Zip(Cursor::new(bytes))
+    } else {
+        extract::Archive::TarGz(Cursor::new(bytes))
+    };
+    extract::extract_uv(&mut archive, install_dir)?;
     Ok(())
 }
 
-pub fn uv_exists(path: &PathBuf) -> Option<PathBuf> {
+pub fn uv_exists(path: &Path) -> Option<PathBuf> {
     let candidates = [
         path.join("uv"),
         path.join("uv.exe"),
@@ -32,125 +58,266 @@ pub fn uv_exists(path: &PathBuf) -> Option<PathBuf> {
         path.join("bin").join("uv.exe"),
     ];
 
-    let uv_bin = match candidates.iter().find(|p| p.exists()).cloned() {
-        Some(p) => p,
+    let uv_bin = candidates.iter().find(|p| p.exists()).cloned();
+    if uv_bin.is_none() {
+        debug_println!(
+            "[uv_handler.uv_exists] - uv binary not found in {}",
+            path.display()
+        );
+    }
+    uv_bin
+}
+
+/// Root of the uv cache, laid out as `<root>/<version>/<target>/uv`.
+pub fn uv_cache_root() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".pycrucible").join("cache").join("uv"))
+}
+
+fn ensure_executable(path: &Path) {
+    #[cfg(unix)]
+    {
+        use std::{fs, os::unix::fs::PermissionsExt};
+
+        let Ok(metadata) = fs::metadata(path) else {
+            eprintln!("uv binary not found at {:?}", path);
+            return;
+        };
+        let mut perms = metadata.permissions();
+        if perms.mode() & 0o777 == 0o755 {
+            debug_println!(
+                "[uv_handler.find_or_download_uv] - uv permissions already 0o755, skipping chmod for {:?}",
+                path
+            );
+            return;
+        }
+        perms.set_mode(0o755);
+        if let Err(e) = fs::set_permissions(path, perms) {
+            eprintln!("Could not chmod uv binary at {:?}: {}", path, e);
+            return;
+        }
+        debug_println!(
+            "[uv_handler.find_or_download_uv] - Set executable permissions for uv at {:?}",
+            path
+        );
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+/// Accepts a uv binary found outside of the cache if its version satisfies `requirement`.
+fn accept_local_uv(
+    path: PathBuf,
+    requirement: &UvVersionRequirement,
+    any_version: bool,
+) -> Option<PathBuf> {
+    if !path.exists() {
+        return None;
+    }
+    ensure_executable(&path);
+    if any_version {
+        debug_println!(
+            "[uv_handler.find_or_download_uv] - Using {:?} without checking its version",
+            path
+        );
+        return Some(path);
+    }
+    match query_uv_version(&path) {
+        Some(version) if requirement.matches(&version) => {
+            debug_println!(
+                "[uv_handler.find_or_download_uv] - {:?} is uv {}, using it",
+                path,
+                version
+            );
+            Some(path)
+        }
+        Some(version) => {
+            debug_println!(
+                "[uv_handler.find_or_download_uv] - {:?} is uv {}, which does not match the requested version",
+                path,
+                version
+            );
+            None
+        }
         None => {
-            eprintln!("uv binary not found.");
-            return None;
+            debug_println!(
+                "[uv_handler.find_or_download_uv] - Could not determine version of {:?}, skipping it",
+                path
+            );
+            None
         }
-    };
-    Some(uv_bin)
+    }
+}
+
+/// Picks the highest cached version satisfying `requirement` for `target`.
+fn find_cached_uv(
+    cache_root: &Path,
+    target: &str,
+    requirement: &UvVersionRequirement,
+) -> Option<PathBuf> {
+    if let UvVersionRequirement::Exact(version) = requirement {
+        return uv_exists(&cache_root.join(version.to_string()).join(target));
+    }
+
+    let entries = std::fs::read_dir(cache_root).ok()?;
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let version = semver::Version::parse(entry.file_name().to_str()?).ok()?;
+            if !requirement.matches(&version) {
+                return None;
+            }
+            let uv_bin = uv_exists(&entry.path().join(target))?;
+            Some((version, uv_bin))
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, uv_bin)| uv_bin)
 }
 
-pub fn find_or_download_uv(cli_uv_path: Option<PathBuf>, uv_version: &str) -> Option<PathBuf> {
+pub fn find_or_download_uv(cli_uv_path: Option<PathBuf>, options: &ToolOptions) -> Option<PathBuf> {
     debug_println!("[uv_handler.find_or_download_uv] - Looking for uv");
 
-    let exe_dir = std::env::current_exe()
-        .expect("Could not find current working directory. Exiting ....")
-        .parent()
-        .unwrap()
-        .to_path_buf();
+    let requirement = match UvVersionRequirement::parse(&options.uv_version) {
+        Ok(requirement) => requirement,
+        Err(e) => {
+            eprintln!("{}", e);
+            return None;
+        }
+    };
     debug_println!(
-        "[uv_handler.find_or_download_uv] - Current working directory: {:?}",
-        exe_dir
+        "[uv_handler.find_or_download_uv] - Requested uv version: {:?}, system uv policy: {:?}",
+        requirement,
+        options.system_uv
     );
-    // Check CLI supplied path first
-    let local_uv = if cli_uv_path.is_some() {
-        debug_println!("CLI supplied uv path detected, using it");
-        let lc_uv = Some(cli_uv_path.as_ref().unwrap().clone());
-        if lc_uv.as_ref().unwrap().exists() {
-            debug_println!("CLI supplied uv path exists");
-            lc_uv
-        } else {
-            debug_println!("CLI supplied uv path does not exist");
-            None
+    let any_version = options.system_uv == SystemUvPolicy::Any;
+
+    // Check CLI supplied path first, it is explicit so the system policy does not apply
+    if let Some(cli_uv) = cli_uv_path {
+        debug_println!("CLI supplied uv path detected: {:?}", cli_uv);
+        if let Some(uv) = accept_local_uv(cli_uv, &requirement, any_version) {
+            return Some(uv);
         }
-    // Check system path next
-    } else if let Ok(path) = which::which("uv") {
-        debug_println!("`which` returned uv path, using it");
-        Some(path)
-    // Check local uv next to binary
-    } else {
-        let local_uv_path = exe_dir.join("uv");
-        if local_uv_path.exists() {
+    }
+
+    if options.system_uv != SystemUvPolicy::Never {
+        // Check system path next
+        if let Ok(path) = which::which("uv") {
+            debug_println!("`which` returned uv path: {:?}", path);
+            if let Some(uv) = accept_local_uv(path, &requirement, any_version) {
+                return Some(uv);
+            }
+        }
+
+        // Check local uv next to binary
+        if let Some(exe_dir) = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf))
+            && let Some(uv) = accept_local_uv(exe_dir.join("uv"), &requirement, any_version)
+        {
             debug_println!("Found uv next to binary, using it");
-            Some(local_uv_path)
-        } else {
-            None
+            return Some(uv);
         }
-    };
+    }
+
     // If not found locally, check cache or download
-    let uv_path = if local_uv.is_some() {
+    debug_println!(
+        "[uv_handler.find_or_download_uv] - uv not found locally, lets see if we have it cached ..."
+    );
+    let target = match platform::target_triple() {
+        Ok(target) => target,
+        Err(e) => {
+            eprintln!("{}", e);
+            return None;
+        }
+    };
+    let Some(cache_root) = uv_cache_root() else {
+        eprintln!("Could not determine home directory for the uv cache");
+        return None;
+    };
+
+    if let Some(uv_bin) = find_cached_uv(&cache_root, &target, &requirement) {
         debug_println!(
-            "[uv_handler.find_or_download_uv] - uv found locally [{:?}], using it",
-            local_uv.as_ref().unwrap().canonicalize()
+            "[uv_handler.find_or_download_uv] - uv found cached at {:?}, using it",
+            uv_bin
         );
-        local_uv
-    } else {
+        ensure_executable(&uv_bin);
+        return Some(uv_bin);
+    }
+
+    let Some(version) = requirement.download_version() else {
+        eprintln!(
+            "No cached uv matches `{}`. Set `uv_version` to an exact version to download it.",
+            options.uv_version
+        );
+        return None;
+    };
+    let install_dir = cache_root.join(&version).join(&target);
+    debug_println!(
+        "[uv_handler.find_or_download_uv] - uv binary not found locally, proceeding to download. uv version: `{}`",
+        version
+    );
+    eprintln!("Downloading `uv` {} ...", version);
+    if let Err(e) = install_uv(&version, &install_dir, options) {
+        eprintln!("uv installation failed: {}", e);
+        return None;
+    }
+    eprintln!("✔ Downloaded `uv` successfully");
+
+    let uv_bin = uv_exists(&install_dir);
+    if let Some(found) = &uv_bin {
         debug_println!(
-            "[uv_handler.find_or_download_uv] - uv not found locally, lets see if we have it cached ..."
+            "[uv_handler.find_or_download_uv] - uv downloaded and found at {:?}, using it",
+            found
         );
-        let home = dirs::home_dir().unwrap();
+        ensure_executable(found);
+    }
+    uv_bin
+}
 
-        let uv_install_root = home.join(".pycrucible").join("cache").join("uv");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
 
-        let uv_bin = uv_exists(&uv_install_root);
-        if uv_bin.is_some() {
-            debug_println!(
-                "[uv_handler.find_or_download_uv] - uv found cached at {:?}, using it",
-                uv_bin.as_ref().unwrap()
-            );
-            return uv_bin;
-        }
+    const TARGET: &str = "x86_64-unknown-linux-gnu";
 
-        debug_println!(
-            "[uv_handler.find_or_download_uv] - uv binary not found locally, proceeding to download. uv version: `{}`",
-            uv_version
-        );
-        let sp = create_spinner_with_message("Downloading `uv` ...");
-        install_uv(uv_version, &uv_install_root).expect("uv installation failed");
-        stop_and_persist_spinner_with_message(sp, "Downloaded `uv` successfully");
+    fn cache_uv(cache_root: &Path, version: &str) {
+        let dir = cache_root.join(version).join(TARGET);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("uv"), b"uv-binary").unwrap();
+    }
 
-        let uv_bin = uv_exists(&uv_install_root);
-        if uv_bin.is_some() {
-            debug_println!(
-                "[uv_handler.find_or_download_uv] - uv downloaded and found at {:?}, using it",
-                uv_bin.as_ref().unwrap()
-            );
-            return uv_bin;
-        }
+    #[test]
+    fn test_find_cached_uv_exact() {
+        let cache = tempdir().unwrap();
+        cache_uv(cache.path(), "0.9.20");
+        cache_uv(cache.path(), "0.9.21");
 
-        return Some(uv_bin.expect("uv binary should exist after download"));
-    };
+        let requirement = UvVersionRequirement::parse("0.9.21").unwrap();
+        assert_eq!(
+            find_cached_uv(cache.path(), TARGET, &requirement),
+            Some(cache.path().join("0.9.21").join(TARGET).join("uv"))
+        );
 
-    #[cfg(unix)]
-    {
-        use std::{fs, os::unix::fs::PermissionsExt};
+        let missing = UvVersionRequirement::parse("0.9.22").unwrap();
+        assert_eq!(find_cached_uv(cache.path(), TARGET, &missing), None);
+    }
 
-        if let Some(ref path) = uv_path {
-            if path.exists() {
-                let mut perms = fs::metadata(path)
-                    .expect("Could not stat uv binary")
-                    .permissions();
-                let current_mode = perms.mode() & 0o777;
-                if current_mode == 0o755 {
-                    debug_println!(
-                        "[uv_handler.find_or_download_uv] - uv permissions already 0o755, skipping chmod for {:?}",
-                        path
-                    );
-                    return uv_path.clone();
-                }
-
-                perms.set_mode(0o755);
-                fs::set_permissions(path, perms).expect("Could not chmod uv binary");
-                debug_println!(
-                    "[uv_handler.find_or_download_uv] - Set executable permissions for uv at {:?}",
-                    path
-                );
-            } else {
-                eprintln!("uv binary not found at {:?}", path);
-            }
-        }
+    #[test]
+    fn test_find_cached_uv_range_picks_highest_for_target() {
+        let cache = tempdir().unwrap();
+        cache_uv(cache.path(), "0.8.0");
+        cache_uv(cache.path(), "0.9.5");
+        cache_uv(cache.path(), "0.9.21");
+        // Cached for another target only
+        let other = cache.path().join("0.9.30").join("aarch64-apple-darwin");
+        fs::create_dir_all(&other).unwrap();
+        fs::write(other.join("uv"), b"uv-binary").unwrap();
+
+        let requirement = UvVersionRequirement::parse(">=0.9, <0.10").unwrap();
+        assert_eq!(
+            find_cached_uv(cache.path(), TARGET, &requirement),
+            Some(cache.path().join("0.9.21").join(TARGET).join("uv"))
+        );
     }
-    uv_path
 }
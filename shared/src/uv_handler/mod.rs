@@ -2,5 +2,10 @@ mod download;
 mod extract;
 mod install;
 mod platform;
+mod progress;
+mod version;
 
+pub use download::{RetryPolicy, build_client, download, sha256_hex};
 pub use install::{find_or_download_uv, install_uv};
+pub use platform::target_triple;
+pub use version::DEFAULT_UV_VERSION;
@@ -1,13 +1,106 @@
-pub fn target_triple() -> String {
+use std::path::Path;
+use std::process::Command;
+
+/// Maps an OS/architecture pair to the target triple used in uv release asset names.
+fn triple_for(os: &str, arch: &str, musl: bool) -> Result<&'static str, String> {
+    let triple = match (os, arch, musl) {
+        ("windows", "x86_64", _) => "x86_64-pc-windows-msvc",
+        ("windows", "aarch64", _) => "aarch64-pc-windows-msvc",
+        ("windows", "x86", _) => "i686-pc-windows-msvc",
+        ("macos", "x86_64", _) => "x86_64-apple-darwin",
+        ("macos", "aarch64", _) => "aarch64-apple-darwin",
+        ("linux", "x86_64", false) => "x86_64-unknown-linux-gnu",
+        ("linux", "x86_64", true) => "x86_64-unknown-linux-musl",
+        ("linux", "aarch64", false) => "aarch64-unknown-linux-gnu",
+        ("linux", "aarch64", true) => "aarch64-unknown-linux-musl",
+        ("linux", "arm", false) => "armv7-unknown-linux-gnueabihf",
+        ("linux", "arm", true) => "armv7-unknown-linux-musleabihf",
+        ("linux", "x86", false) => "i686-unknown-linux-gnu",
+        ("linux", "x86", true) => "i686-unknown-linux-musl",
+        _ => {
+            return Err(format!(
+                "Unsupported platform for uv download: {os}/{arch}{}. Install uv manually and put it on PATH.",
+                if musl { " (musl)" } else { "" }
+            ));
+        }
+    };
+    Ok(triple)
+}
+
+/// Detects whether the host uses musl rather than glibc.
+/// The runner may be a glibc build running on a musl system (or vice versa), so this
+/// looks at the host instead of relying only on how we were compiled.
+fn is_musl() -> bool {
+    if std::env::consts::OS != "linux" {
+        return false;
+    }
+    // `ldd --version` prints "musl libc" (to stderr) on musl systems
+    if let Ok(output) = Command::new("ldd").arg("--version").output() {
+        let combined = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .to_lowercase();
+        if combined.contains("musl") {
+            return true;
+        }
+        if combined.contains("glibc") || combined.contains("gnu libc") {
+            return false;
+        }
+    }
+    if Path::new("/lib")
+        .read_dir()
+        .map(|entries| {
+            entries
+                .flatten()
+                .any(|e| e.file_name().to_string_lossy().starts_with("ld-musl-"))
+        })
+        .unwrap_or(false)
+    {
+        return true;
+    }
+    cfg!(target_env = "musl")
+}
+
+pub fn target_triple() -> Result<String, String> {
     let os = std::env::consts::OS;
     let arch = std::env::consts::ARCH;
 
-    match (os, arch) {
-        ("windows", "x86_64") => "x86_64-pc-windows-msvc",
-        ("linux", "x86_64") => "x86_64-unknown-linux-gnu",
-        ("macos", "x86_64") => "x86_64-apple-darwin",
-        ("macos", "aarch64") => "aarch64-apple-darwin",
-        _ => panic!("Unsupported platform: {os}/{arch}"),
+    triple_for(os, arch, is_musl()).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_triple_for_supported_platforms() {
+        assert_eq!(
+            triple_for("linux", "aarch64", false),
+            Ok("aarch64-unknown-linux-gnu")
+        );
+        assert_eq!(
+            triple_for("linux", "x86_64", true),
+            Ok("x86_64-unknown-linux-musl")
+        );
+        assert_eq!(
+            triple_for("linux", "arm", false),
+            Ok("armv7-unknown-linux-gnueabihf")
+        );
+        assert_eq!(
+            triple_for("windows", "aarch64", false),
+            Ok("aarch64-pc-windows-msvc")
+        );
+        assert_eq!(
+            triple_for("macos", "aarch64", false),
+            Ok("aarch64-apple-darwin")
+        );
+    }
+
+    #[test]
+    fn test_triple_for_unsupported_platform() {
+        let err = triple_for("freebsd", "x86_64", false).unwrap_err();
+        assert!(err.contains("freebsd/x86_64"));
     }
-    .to_string()
 }
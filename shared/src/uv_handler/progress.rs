@@ -0,0 +1,129 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use std::io::IsTerminal;
+
+// Without a TTY a line is logged every time this many more percent (or bytes, when the
+// total size is unknown) have been downloaded.
+const LOG_EVERY_PERCENT: u64 = 10;
+const LOG_EVERY_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Reports byte-level download progress on stderr: a progress bar on TTYs, periodic log lines
+/// otherwise. Stdout belongs to the app, it may be piped into another program.
+pub struct DownloadProgress {
+    label: String,
+    bar: Option<ProgressBar>,
+    total: Option<u64>,
+    downloaded: u64,
+    last_logged: u64,
+}
+
+impl DownloadProgress {
+    pub fn new(label: &str) -> Self {
+        let bar = if std::io::stderr().is_terminal() {
+            let bar = ProgressBar::new(0);
+            bar.set_style(
+                ProgressStyle::with_template(
+                    "{msg} [{bar:30}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
+                )
+                .unwrap_or_else(|_| ProgressStyle::default_bar())
+                .progress_chars("=> "),
+            );
+            bar.set_message(label.to_string());
+            Some(bar)
+        } else {
+            None
+        };
+        DownloadProgress {
+            label: label.to_string(),
+            bar,
+            total: None,
+            downloaded: 0,
+            last_logged: 0,
+        }
+    }
+
+    /// Starts (or restarts after a retry) at `downloaded` bytes out of `total`.
+    pub fn start(&mut self, downloaded: u64, total: Option<u64>) {
+        self.downloaded = downloaded;
+        self.last_logged = downloaded;
+        self.total = total;
+        match &self.bar {
+            Some(bar) => {
+                if let Some(total) = total {
+                    bar.set_length(total);
+                }
+                bar.set_position(downloaded);
+            }
+            None if downloaded > 0 => {
+                eprintln!(
+                    "{}: resuming at {}",
+                    self.label,
+                    format_progress(downloaded, total)
+                );
+            }
+            None => {}
+        }
+    }
+
+    pub fn advance(&mut self, bytes: u64) {
+        self.downloaded += bytes;
+        if let Some(bar) = &self.bar {
+            bar.set_position(self.downloaded);
+            return;
+        }
+        let should_log = match self.total {
+            Some(total) if total > 0 => {
+                (self.downloaded - self.last_logged) * 100 / total >= LOG_EVERY_PERCENT
+            }
+            _ => self.downloaded - self.last_logged >= LOG_EVERY_BYTES,
+        };
+        if should_log {
+            self.last_logged = self.downloaded;
+            eprintln!(
+                "{}: {}",
+                self.label,
+                format_progress(self.downloaded, self.total)
+            );
+        }
+    }
+
+    pub fn finish(self) {
+        match self.bar {
+            Some(bar) => bar.finish_and_clear(),
+            None => eprintln!(
+                "{}: done ({})",
+                self.label,
+                format_progress(self.downloaded, self.total)
+            ),
+        }
+    }
+}
+
+fn format_mb(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+fn format_progress(downloaded: u64, total: Option<u64>) -> String {
+    match total {
+        Some(total) if total > 0 => format!(
+            "{} / {} ({}%)",
+            format_mb(downloaded),
+            format_mb(total),
+            downloaded * 100 / total
+        ),
+        _ => format_mb(downloaded),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_progress() {
+        assert_eq!(
+            format_progress(5 * 1024 * 1024, Some(20 * 1024 * 1024)),
+            "5.0 MB / 20.0 MB (25%)"
+        );
+        assert_eq!(format_progress(1024 * 1024, None), "1.0 MB");
+    }
+}
@@ -0,0 +1,118 @@
+use crate::debug_println;
+use semver::{Version, VersionReq};
+use std::path::Path;
+use std::process::Command;
+
+pub const DEFAULT_UV_VERSION: &str = "0.9.21";
+
+/// What `ToolOptions.uv_version` asks for.
+#[derive(Debug, PartialEq)]
+pub enum UvVersionRequirement {
+    // Empty `uv_version`, any uv will do
+    Any,
+    Exact(Version),
+    Range(VersionReq),
+}
+
+impl UvVersionRequirement {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let raw = raw.trim().trim_start_matches('v');
+        if raw.is_empty() {
+            return Ok(UvVersionRequirement::Any);
+        }
+        // `VersionReq` would read a bare `0.9.21` as `^0.9.21`, so try an exact version first
+        if let Ok(version) = Version::parse(raw) {
+            return Ok(UvVersionRequirement::Exact(version));
+        }
+        VersionReq::parse(raw)
+            .map(UvVersionRequirement::Range)
+            .map_err(|e| format!("Invalid uv version requirement `{}`: {}", raw, e))
+    }
+
+    pub fn matches(&self, version: &Version) -> bool {
+        match self {
+            UvVersionRequirement::Any => true,
+            UvVersionRequirement::Exact(exact) => exact == version,
+            UvVersionRequirement::Range(req) => req.matches(version),
+        }
+    }
+
+    /// The version to download when nothing suitable is installed.
+    /// Ranges cannot be resolved without querying the release list, so they have none.
+    pub fn download_version(&self) -> Option<String> {
+        match self {
+            UvVersionRequirement::Any => Some(DEFAULT_UV_VERSION.to_string()),
+            UvVersionRequirement::Exact(exact) => Some(exact.to_string()),
+            UvVersionRequirement::Range(_) => None,
+        }
+    }
+}
+
+/// Parses the output of `uv --version`, e.g. `uv 0.9.21 (0dc9556ad 2025-12-30)`.
+pub fn parse_uv_version_output(output: &str) -> Option<Version> {
+    let mut parts = output.split_whitespace();
+    if parts.next()? != "uv" {
+        return None;
+    }
+    Version::parse(parts.next()?).ok()
+}
+
+/// Runs `uv --version` for the binary at `uv_path`.
+pub fn query_uv_version(uv_path: &Path) -> Option<Version> {
+    let output = Command::new(uv_path).arg("--version").output().ok()?;
+    if !output.status.success() {
+        debug_println!(
+            "[uv_handler.query_uv_version] - `{} --version` failed",
+            uv_path.display()
+        );
+        return None;
+    }
+    parse_uv_version_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_requirement() {
+        assert_eq!(
+            UvVersionRequirement::parse("").unwrap(),
+            UvVersionRequirement::Any
+        );
+        assert_eq!(
+            UvVersionRequirement::parse("0.9.21").unwrap(),
+            UvVersionRequirement::Exact(Version::new(0, 9, 21))
+        );
+        assert!(matches!(
+            UvVersionRequirement::parse(">=0.9, <0.10").unwrap(),
+            UvVersionRequirement::Range(_)
+        ));
+        assert!(UvVersionRequirement::parse("not a version").is_err());
+    }
+
+    #[test]
+    fn test_requirement_matches() {
+        let exact = UvVersionRequirement::parse("0.9.21").unwrap();
+        assert!(exact.matches(&Version::new(0, 9, 21)));
+        assert!(!exact.matches(&Version::new(0, 9, 22)));
+
+        let range = UvVersionRequirement::parse(">=0.9, <0.10").unwrap();
+        assert!(range.matches(&Version::new(0, 9, 30)));
+        assert!(!range.matches(&Version::new(0, 10, 0)));
+        assert_eq!(range.download_version(), None);
+    }
+
+    #[test]
+    fn test_parse_uv_version_output() {
+        assert_eq!(
+            parse_uv_version_output("uv 0.9.21 (0dc9556ad 2025-12-30)\n"),
+            Some(Version::new(0, 9, 21))
+        );
+        assert_eq!(
+            parse_uv_version_output("uv 0.4.0\n"),
+            Some(Version::new(0, 4, 0))
+        );
+        assert_eq!(parse_uv_version_output("ruff 0.1.0"), None);
+    }
+}